@@ -0,0 +1,39 @@
+use configster::ParseOptions;
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+/// A configuration with sections, comments, and options with and without
+/// attributes.
+fn generate(lines: usize) -> String {
+    let mut s = String::new();
+    for i in 0..lines {
+        match i % 10 {
+            0 => s.push_str(&format!("[section_{}]\n", i / 10)),
+            1 => s.push_str("# a comment\n"),
+            2 => s.push('\n'),
+            3 => s.push_str(&format!("max_users_{} = {}\n", i, i)),
+            4 => s.push_str(&format!("flag_{}\n", i)),
+            _ => s.push_str(&format!(
+                "option_{} = Blue, light, shiny, matte, large, round\n",
+                i
+            )),
+        }
+    }
+    s
+}
+
+fn bench_parse_str(c: &mut Criterion) {
+    let data = generate(10_000);
+    let mut group = c.benchmark_group("parse_str");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("10k lines", |b| {
+        b.iter(|| configster::parse_str(black_box(&data), ',').unwrap())
+    });
+    let opts = ParseOptions::with_delimiter(", ");
+    group.bench_function("10k lines, str delimiter", |b| {
+        b.iter(|| configster::parse_str_with(black_box(&data), &opts).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse_str);
+criterion_main!(benches);
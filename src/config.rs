@@ -0,0 +1,521 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Deref;
+use core::str::FromStr;
+
+use crate::merge::{insert_option, merge_into};
+use crate::query::flags;
+use crate::write::write_options;
+use crate::{parse_str, OptionProperties, ParseError, Value, DEFAULT_ATTR_DELIMIT_CHAR};
+
+/// A parsed configuration; a wrapper around the vector of
+/// [OptionProperties](struct.OptionProperties.html) returned by the parse
+/// functions. It dereferences to a slice of the options.
+///
+/// # Examples
+///
+/// Parsing a configuration embedded in the binary:
+///
+/// ```
+/// use configster::Config;
+///
+/// let cfg: Config = "max_users = 30\nDelayOff".parse().unwrap();
+/// assert_eq!(cfg.len(), 2);
+/// assert_eq!(cfg[0].value.primary, "30");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    options: Vec<OptionProperties>,
+    /// The attribute delimiter the configuration was parsed with, used when
+    /// it is written back as text
+    attr_delimiter: char,
+}
+
+impl Config {
+    /// Creates a `Config` from a vector of options, with
+    /// [DEFAULT_ATTR_DELIMIT_CHAR](constant.DEFAULT_ATTR_DELIMIT_CHAR.html) as
+    /// the attribute delimiter.
+    pub fn new(options: Vec<OptionProperties>) -> Self {
+        Self::with_delimiter(options, DEFAULT_ATTR_DELIMIT_CHAR)
+    }
+
+    /// Creates a `Config` from a vector of options parsed with the attribute
+    /// delimiter `attr_delimit_char`, which is used when the configuration
+    /// is displayed.
+    pub fn with_delimiter(options: Vec<OptionProperties>, attr_delimit_char: char) -> Self {
+        Self {
+            options,
+            attr_delimiter: attr_delimit_char,
+        }
+    }
+
+    /// Returns the attribute delimiter.
+    pub fn delimiter(&self) -> char {
+        self.attr_delimiter
+    }
+
+    /// Returns the options as a slice.
+    pub fn options(&self) -> &[OptionProperties] {
+        &self.options
+    }
+
+    /// Consumes the `Config`, returning the options vector.
+    pub fn into_vec(self) -> Vec<OptionProperties> {
+        self.options
+    }
+
+    /// Sets the value of the first option named `option`, or adds a new
+    /// option outside of any section if there's none. The new option goes
+    /// before the first section, so that it is still outside of any section
+    /// when the configuration is written and parsed again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use configster::{Config, Value};
+    ///
+    /// let mut cfg: Config = "max_users = 30".parse().unwrap();
+    /// cfg.set("max_users", Value { primary: "50".to_string(), attributes: vec![] });
+    /// cfg.set("DelayOff", Value { primary: String::new(), attributes: vec![] });
+    /// assert_eq!(cfg.len(), 2);
+    /// assert_eq!(cfg[0].value.primary, "50");
+    /// ```
+    pub fn set(&mut self, option: &str, value: Value) {
+        match self.options.iter_mut().find(|o| o.option == option) {
+            Some(opt_props) => opt_props.value = value,
+            None => {
+                let opt_props =
+                    OptionProperties::new(option.into(), value.primary, value.attributes);
+                insert_option(&mut self.options, opt_props);
+            }
+        }
+    }
+
+    /// Returns the value of the first option named `option`, after adding the
+    /// option outside of any section with the value returned by `f` if
+    /// there's none. As with [set](#method.set), the new option goes before
+    /// the first section.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use configster::{Config, Value};
+    ///
+    /// let mut cfg: Config = "max_users = 30".parse().unwrap();
+    /// let port = cfg.get_or_insert_with("port", || Value { primary: "80".to_string(), attributes: vec![] });
+    /// assert_eq!(port.primary, "80");
+    /// let users = cfg.get_or_insert_with("max_users", || unreachable!());
+    /// assert_eq!(users.primary, "30");
+    /// assert_eq!(cfg.len(), 2);
+    /// ```
+    pub fn get_or_insert_with(&mut self, option: &str, f: impl FnOnce() -> Value) -> &Value {
+        let i = match self.options.iter().position(|o| o.option == option) {
+            Some(i) => i,
+            None => {
+                let value = f();
+                let opt_props =
+                    OptionProperties::new(option.into(), value.primary, value.attributes);
+                insert_option(&mut self.options, opt_props)
+            }
+        };
+        &self.options[i].value
+    }
+
+    /// Returns a `Config` holding the options in section `name` (`None` for
+    /// the options before any section header), which keep their section. See
+    /// also [section_stripped](#method.section_stripped).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use configster::Config;
+    ///
+    /// let cfg: Config = "debug\n[db]\nport = 5432\n[web]\nport = 80".parse().unwrap();
+    /// let db = cfg.section(Some("db"));
+    /// assert_eq!(db.len(), 1);
+    /// assert_eq!(db[0].value.primary, "5432");
+    /// ```
+    pub fn section(&self, name: Option<&str>) -> Config {
+        let options = self
+            .options
+            .iter()
+            .filter(|o| o.section.as_deref() == name)
+            .cloned()
+            .collect();
+        Self::with_delimiter(options, self.attr_delimiter)
+    }
+
+    /// Like [section](#method.section), but the section of the options is
+    /// removed, so that they look like a configuration of their own.
+    pub fn section_stripped(&self, name: Option<&str>) -> Config {
+        let mut cfg = self.section(name);
+        for opt_props in &mut cfg.options {
+            opt_props.section = None;
+        }
+        cfg
+    }
+
+    /// Moves the options in section `old` to section `new`, e.g. when a
+    /// section is renamed from "[db]" to "[database]"; the other options are
+    /// left as they are. If there are options in `new` already, the two
+    /// sections are merged; see also [try_rename_section](#method.try_rename_section).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use configster::Config;
+    ///
+    /// let mut cfg: Config = "[db]\nport = 5432\n[web]\nport = 80".parse().unwrap();
+    /// cfg.rename_section("db", "database");
+    /// assert_eq!(cfg.to_string(), "[database]\nport = 5432\n\n[web]\nport = 80\n");
+    /// ```
+    pub fn rename_section(&mut self, old: &str, new: &str) {
+        for opt_props in &mut self.options {
+            if opt_props.section.as_deref() == Some(old) {
+                opt_props.section = Some(new.into());
+            }
+        }
+    }
+
+    /// Like [rename_section](#method.rename_section), but if there are
+    /// options in section `new` already, nothing is renamed and false is
+    /// returned.
+    pub fn try_rename_section(&mut self, old: &str, new: &str) -> bool {
+        if old != new
+            && self
+                .options
+                .iter()
+                .any(|o| o.section.as_deref() == Some(new))
+        {
+            return false;
+        }
+        self.rename_section(old, new);
+        true
+    }
+
+    /// Returns the names of the flag options (those without a value) in
+    /// section `name`, in the order they appear, e.g. the enabled features
+    /// listed under a "[features]" header. See also
+    /// [section_flags](fn.section_flags.html).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use configster::Config;
+    ///
+    /// let cfg: Config = "sync\n[features]\ndark_mode\nbeta = off\nsync".parse().unwrap();
+    /// assert_eq!(cfg.flags_in_section("features"), vec!["dark_mode", "sync"]);
+    /// ```
+    pub fn flags_in_section(&self, name: &str) -> Vec<&str> {
+        flags(&self.options, name).collect()
+    }
+
+    /// Removes every option named `option`, returning the value of the first
+    /// one, or `None` if there was no such option.
+    pub fn remove(&mut self, option: &str) -> Option<Value> {
+        let i = self.options.iter().position(|o| o.option == option)?;
+        let removed = self.options.remove(i);
+        self.options.retain(|o| o.option != option);
+        Some(removed.value)
+    }
+
+    /// Merges the options of `over` into the configuration, as
+    /// [merge](fn.merge.html) does, without building a new vector: options
+    /// with the same name and section are replaced in place and the others
+    /// are added (those without a section before the first section). The
+    /// options are matched through an index, so merging takes O((n + m) log n)
+    /// time. The attribute delimiter of `self` is kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use configster::Config;
+    ///
+    /// let mut cfg: Config = "max_users = 30\ncolor = blue".parse().unwrap();
+    /// for layer in &["color = red", "DelayOff"] {
+    ///     cfg.merge_in_place(layer.parse().unwrap());
+    /// }
+    /// assert_eq!(cfg.to_string(), "max_users = 30\ncolor = red\nDelayOff\n");
+    /// ```
+    pub fn merge_in_place(&mut self, over: Config) {
+        merge_into(&mut self.options, over.options);
+    }
+
+    /// Validates the configuration with `f`, which returns the list of
+    /// problems it finds, for constraints involving several options. Checks
+    /// such as [require](fn.require.html) can be combined in it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use configster::Config;
+    ///
+    /// let check = |cfg: &Config| {
+    ///     let mut errors = configster::require(cfg, &["port"]).err().unwrap_or_default();
+    ///     let tls = cfg.iter().any(|o| o.option == "tls" && o.value.is_truthy());
+    ///     if tls && !cfg.iter().any(|o| o.option == "cert") {
+    ///         errors.push("tls is on but there is no cert".to_string());
+    ///     }
+    ///     if errors.is_empty() { Ok(()) } else { Err(errors) }
+    /// };
+    /// let cfg: Config = "port = 443\ntls = on\ncert = /etc/app.pem".parse().unwrap();
+    /// assert!(cfg.validate_with(check).is_ok());
+    /// let cfg: Config = "tls = on".parse().unwrap();
+    /// assert_eq!(cfg.validate_with(check).unwrap_err().len(), 2);
+    /// ```
+    pub fn validate_with<F>(&self, f: F) -> Result<(), Vec<String>>
+    where
+        F: FnOnce(&Config) -> Result<(), Vec<String>>,
+    {
+        f(self)
+    }
+}
+
+impl From<Vec<OptionProperties>> for Config {
+    fn from(options: Vec<OptionProperties>) -> Self {
+        Self::new(options)
+    }
+}
+
+impl Deref for Config {
+    type Target = [OptionProperties];
+
+    fn deref(&self) -> &Self::Target {
+        &self.options
+    }
+}
+
+/// Parses a configuration using
+/// [DEFAULT_ATTR_DELIMIT_CHAR](constant.DEFAULT_ATTR_DELIMIT_CHAR.html) as the
+/// attribute delimiter.
+impl FromStr for Config {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_str(s, DEFAULT_ATTR_DELIMIT_CHAR).map(Self::new)
+    }
+}
+
+/// Writes the configuration in the configuration file format, with the
+/// attributes separated by the stored delimiter and a header wherever the
+/// section changes. Comments and the original formatting aren't kept.
+///
+/// # Examples
+///
+/// ```
+/// use configster::Config;
+///
+/// let cfg: Config = "[db]\nport=5432,tcp\n# comment\nDelayOff".parse().unwrap();
+/// assert_eq!(cfg.to_string(), "[db]\nport = 5432, tcp\nDelayOff\n");
+/// ```
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut delim = [0; 4];
+        write_options(
+            f,
+            &self.options,
+            self.attr_delimiter.encode_utf8(&mut delim),
+        )
+    }
+}
+
+#[test]
+fn test_config_from_str() {
+    let cfg: Config = "option = Blue, light, shiny\n# comment\nDelayOff\n"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        cfg,
+        Config::new(config![
+            "option" => "Blue", ["light", "shiny"];
+            "DelayOff" => "";
+        ])
+    );
+
+    // Block values aren't enabled by default
+    let cfg: Config = "script = <<END\n".parse().unwrap();
+    assert_eq!(cfg[0].value.primary, "<<END");
+}
+
+#[test]
+fn test_config_set_remove() {
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    let mut cfg: Config = "a = 1\n[s]\nb = 2, x\na = 3".parse().unwrap();
+    cfg.set(
+        "b",
+        Value {
+            primary: "20".to_string(),
+            attributes: vec!["y".to_string()],
+        },
+    );
+    assert_eq!(cfg[1].value.primary, "20");
+    assert_eq!(cfg[1].value.attributes, vec!["y"]);
+    assert_eq!(cfg[1].section.as_deref(), Some("s"));
+
+    cfg.set(
+        "c",
+        Value {
+            primary: "4".to_string(),
+            attributes: vec![],
+        },
+    );
+    assert_eq!(cfg.len(), 4);
+    assert_eq!(cfg[1].option, "c");
+    assert_eq!(cfg[1].section, None);
+    // The new option stays outside of the section when written
+    let reparsed: Config = cfg.to_string().parse().unwrap();
+    assert_eq!(reparsed, cfg);
+
+    assert_eq!(cfg.remove("a").unwrap().primary, "1");
+    assert_eq!(cfg.len(), 2);
+    assert!(cfg.iter().all(|o| o.option != "a"));
+    assert_eq!(cfg.remove("a"), None);
+}
+
+#[test]
+fn test_config_display() {
+    use alloc::string::ToString;
+
+    let data = "top = 1\n[a]\nx = 1; 2\n[b]\nflag\ny = ; z";
+    let cfg = Config::with_delimiter(crate::parse_str(data, ';').unwrap(), ';');
+    let s = cfg.to_string();
+    assert_eq!(s, "top = 1\n\n[a]\nx = 1; 2\n\n[b]\nflag\ny = ; z\n");
+    assert_eq!(crate::parse_str(&s, ';').unwrap(), cfg.options());
+    assert_eq!(Config::new(Vec::new()).to_string(), "");
+}
+
+#[test]
+fn test_config_get_or_insert_with() {
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    let mut cfg: Config = "[s]\na = 1\na = 2".parse().unwrap();
+    assert_eq!(cfg.get_or_insert_with("a", || unreachable!()).primary, "1");
+    let b = cfg.get_or_insert_with("b", || Value {
+        primary: "x".to_string(),
+        attributes: vec!["y".to_string()],
+    });
+    assert_eq!(b.attributes, vec!["y"]);
+    assert_eq!(cfg.len(), 3);
+    assert_eq!(cfg[0].option, "b");
+    assert_eq!(cfg[0].section, None);
+    // The new option stays outside of the section when written
+    let reparsed: Config = cfg.to_string().parse().unwrap();
+    assert_eq!(reparsed, cfg);
+}
+
+#[test]
+fn test_config_section() {
+    use alloc::string::ToString;
+
+    let cfg = Config::with_delimiter(
+        crate::parse_str(
+            "a = 1\n[db]\nport = 5432; x\n[web]\nport = 80\n[db]\nuser = pg",
+            ';',
+        )
+        .unwrap(),
+        ';',
+    );
+    let db = cfg.section(Some("db"));
+    assert_eq!(db.len(), 2);
+    assert_eq!(db.delimiter(), ';');
+    assert_eq!(db.to_string(), "[db]\nport = 5432; x\nuser = pg\n");
+    let db = cfg.section_stripped(Some("db"));
+    assert_eq!(db.to_string(), "port = 5432; x\nuser = pg\n");
+    assert_eq!(cfg.section(None).len(), 1);
+    assert!(cfg.section(Some("missing")).is_empty());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_config_rename_section() {
+    let data = "a = 1\n[db]\nport = 5432\n[database]\nuser = me\n[web]\nport = 80\n[db]\nhost = h";
+    let mut cfg: Config = data.parse().unwrap();
+    let sections =
+        |cfg: &Config| -> Vec<Option<String>> { cfg.iter().map(|o| o.section.clone()).collect() };
+    let expected = |names: &[Option<&str>]| -> Vec<Option<String>> {
+        names.iter().map(|n| n.map(String::from)).collect()
+    };
+
+    assert!(!cfg.try_rename_section("db", "database"));
+    assert_eq!(cfg, data.parse().unwrap());
+    assert!(cfg.try_rename_section("db", "db"));
+    assert!(cfg.try_rename_section("web", "http"));
+    assert!(cfg.try_rename_section("missing", "x"));
+    assert_eq!(
+        sections(&cfg),
+        expected(&[None, Some("db"), Some("database"), Some("http"), Some("db")])
+    );
+
+    cfg.rename_section("db", "database");
+    assert_eq!(
+        sections(&cfg),
+        expected(&[
+            None,
+            Some("database"),
+            Some("database"),
+            Some("http"),
+            Some("database")
+        ])
+    );
+    assert_eq!(cfg.section(Some("database")).len(), 3);
+}
+
+#[test]
+fn test_config_flags_in_section() {
+    let cfg: Config =
+        "a\n[features]\ndark_mode\nb = , x\nnot a flag\n[other]\nc\n[features]\nsync\ndark_mode"
+            .parse()
+            .unwrap();
+    assert_eq!(
+        cfg.flags_in_section("features"),
+        vec!["dark_mode", "sync", "dark_mode"]
+    );
+    assert_eq!(cfg.flags_in_section("other"), vec!["c"]);
+    assert!(cfg.flags_in_section("none").is_empty());
+}
+
+#[test]
+fn test_config_merge_in_place() {
+    let mut cfg = Config::with_delimiter(
+        parse_str("a = 1; x\n[db]\nport = 5432\nhost = a", ';').unwrap(),
+        ';',
+    );
+    let over: Config = "[db]\nport = 6543\nuser = me\n[web]\nport = 80"
+        .parse()
+        .unwrap();
+    let expected = crate::merge(cfg.clone().into_vec(), over.clone().into_vec());
+    cfg.merge_in_place(over);
+    assert_eq!(cfg.options(), &expected[..]);
+    assert_eq!(cfg.delimiter(), ';');
+    assert_eq!(cfg.len(), 5);
+
+    cfg.merge_in_place(Config::new(Vec::new()));
+    assert_eq!(cfg.options(), &expected[..]);
+
+    // A new option without a section goes before the sections, and stays
+    // there when written
+    let over = parse_str("a = 2\nb = 3; y\n[web]\nport = 8080", ';').unwrap();
+    cfg.merge_in_place(Config::with_delimiter(over, ';'));
+    let names: Vec<(Option<&str>, &str)> = cfg
+        .iter()
+        .map(|o| (o.section.as_deref(), o.option.as_str()))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            (None, "a"),
+            (None, "b"),
+            (Some("db"), "port"),
+            (Some("db"), "host"),
+            (Some("db"), "user"),
+            (Some("web"), "port")
+        ]
+    );
+    assert_eq!(cfg[1].value.attributes, vec!["y"]);
+    let reparsed = Config::with_delimiter(parse_str(&cfg.to_string(), ';').unwrap(), ';');
+    assert_eq!(reparsed, cfg);
+}
@@ -0,0 +1,208 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+#[cfg(feature = "std")]
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io;
+
+#[cfg(feature = "std")]
+use crate::write::write_option;
+use crate::{Config, OptionProperties};
+
+/// A difference between two configurations, as returned by
+/// [diff](fn.diff.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change<'a> {
+    /// An option of the old configuration that isn't in the new one.
+    Removed(&'a OptionProperties),
+    /// An option of the new configuration that isn't in the old one.
+    Added(&'a OptionProperties),
+    /// An option whose value is different in the new configuration.
+    Changed {
+        old: &'a OptionProperties,
+        new: &'a OptionProperties,
+    },
+}
+
+impl<'a> Change<'a> {
+    /// The section of the option that changed.
+    pub fn section(&self) -> Option<&'a str> {
+        match self {
+            Change::Removed(o) | Change::Added(o) | Change::Changed { new: o, .. } => {
+                o.section.as_deref()
+            }
+        }
+    }
+}
+
+/// Returns the changes from `old` to `new`. Options are matched by name and
+/// section (the occurrences of a repeated option in order). The changes to
+/// the options without a section come first, then the others in the order
+/// of the old configuration, followed by the added options.
+///
+/// # Examples
+///
+/// ```
+/// use configster::{Change, Config};
+///
+/// let old: Config = "max_users = 30\nDelayOff".parse().unwrap();
+/// let new: Config = "max_users = 50".parse().unwrap();
+/// let changes = configster::diff(&old, &new);
+/// assert_eq!(changes.len(), 2);
+/// assert!(matches!(changes[0], Change::Changed { new, .. } if new.value.primary == "50"));
+/// assert!(matches!(changes[1], Change::Removed(o) if o.option == "DelayOff"));
+/// ```
+pub fn diff<'a>(old: &'a Config, new: &'a Config) -> Vec<Change<'a>> {
+    let new_index: BTreeMap<_, _> = occurrences(new).collect();
+    let mut matched = BTreeSet::new();
+    let mut changes = Vec::new();
+    for (key, opt_props) in occurrences(old) {
+        match new_index.get(&key) {
+            Some(&new_props) if new_props.value == opt_props.value => {}
+            Some(&new_props) => changes.push(Change::Changed {
+                old: opt_props,
+                new: new_props,
+            }),
+            None => changes.push(Change::Removed(opt_props)),
+        }
+        matched.insert(key);
+    }
+    changes.extend(
+        occurrences(new)
+            .filter(|(key, _)| !matched.contains(key))
+            .map(|(_, opt_props)| Change::Added(opt_props)),
+    );
+    // There is no header for leaving a section
+    changes.sort_by_key(|c| c.section().is_some());
+    changes
+}
+
+/// Pairs each option with its (section, name, occurrence) key.
+fn occurrences(
+    cfg: &Config,
+) -> impl Iterator<Item = ((Option<&str>, &str, usize), &OptionProperties)> {
+    let mut counts: BTreeMap<(Option<&str>, &str), usize> = BTreeMap::new();
+    cfg.iter().map(move |o| {
+        let count = counts
+            .entry((o.section.as_deref(), o.option.as_str()))
+            .or_insert(0);
+        *count += 1;
+        ((o.section.as_deref(), o.option.as_str(), *count - 1), o)
+    })
+}
+
+/// Writes the changes from `old` to `new` (see [diff](fn.diff.html)), one
+/// line per option: "-" and the option line for a removed option, "+" for an
+/// added one, and both for a changed value. The changes in a section follow
+/// a " [name]" line. Nothing is written if the options are the same.
+///
+/// # Examples
+///
+/// ```
+/// use configster::Config;
+///
+/// let old: Config = "max_users = 30\ncolor = blue\nDelayOff".parse().unwrap();
+/// let new: Config = "max_users = 50\ncolor = blue\nport = 80".parse().unwrap();
+/// let mut out = Vec::new();
+/// configster::diff_to_writer(&old, &new, &mut out).unwrap();
+/// assert_eq!(
+///     String::from_utf8(out).unwrap(),
+///     "-max_users = 30\n+max_users = 50\n-DelayOff\n+port = 80\n"
+/// );
+/// ```
+#[cfg(feature = "std")]
+pub fn diff_to_writer<W: io::Write>(old: &Config, new: &Config, w: &mut W) -> io::Result<()> {
+    // The section of the last change written
+    let mut section = None;
+    for change in diff(old, new) {
+        if change.section() != section {
+            if let Some(name) = change.section() {
+                writeln!(w, " [{}]", name)?;
+            }
+            section = change.section();
+        }
+        match change {
+            Change::Removed(o) => write_change(w, '-', o, old.delimiter())?,
+            Change::Added(o) => write_change(w, '+', o, new.delimiter())?,
+            Change::Changed {
+                old: old_props,
+                new: new_props,
+            } => {
+                write_change(w, '-', old_props, old.delimiter())?;
+                write_change(w, '+', new_props, new.delimiter())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+fn write_change<W: io::Write>(
+    w: &mut W,
+    sign: char,
+    opt_props: &OptionProperties,
+    attr_delimiter: char,
+) -> io::Result<()> {
+    let mut line = String::new();
+    let mut delim = [0; 4];
+    // Writing to a String doesn't fail
+    let _ = write_option(
+        &mut line,
+        opt_props,
+        "=",
+        attr_delimiter.encode_utf8(&mut delim),
+        true,
+        true,
+    );
+    writeln!(w, "{}{}", sign, line)
+}
+
+#[test]
+fn test_diff() {
+    let parse = |s: &str| Config::new(crate::parse_str(s, ',').unwrap());
+    let old = parse("a = 1\nb = 2\n[db]\nport = 5432\nhost = a\nhost = b");
+    let new = parse("b = 3\n[db]\nhost = a\nport = 5432\nuser = pg\n");
+    let changes = diff(&old, &new);
+    assert_eq!(
+        changes,
+        [
+            Change::Removed(&old[0]),
+            Change::Changed {
+                old: &old[1],
+                new: &new[0]
+            },
+            Change::Removed(&old[4]),
+            Change::Added(&new[3]),
+        ]
+    );
+    assert_eq!(changes[1].section(), None);
+    assert_eq!(changes[3].section(), Some("db"));
+    assert!(diff(&old, &old).is_empty());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_diff_to_writer() {
+    let diff = |old: &str, new: &str| {
+        let old = Config::with_delimiter(crate::parse_str(old, ';').unwrap(), ';');
+        let new = Config::with_delimiter(crate::parse_str(new, ';').unwrap(), ';');
+        let mut out = Vec::new();
+        diff_to_writer(&old, &new, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    let old = "a = 1\nb = 2; x\n[db]\nport = 5432\nhost = a\nhost = b\n[web]\nport = 80";
+    assert_eq!(diff(old, old), "");
+    let new = "b = 2;x\na = 1\n[db]\nport = 5433\nhost = a\n[web]\nport = 80; tls\n[db]\nuser = pg";
+    assert_eq!(
+        diff(old, new),
+        " [db]\n-port = 5432\n+port = 5433\n-host = b\n [web]\n-port = 80\n+port = 80; tls\n [db]\n+user = pg\n"
+    );
+    assert_eq!(diff("", "DelayOff\n[s]\nx"), "+DelayOff\n [s]\n+x\n");
+
+    // A top-level option added after the changes in a section is written
+    // before them, since it couldn't be told apart from the section's
+    assert_eq!(
+        diff("[db]\nport = 1", "a = 1\n[db]\nport = 2"),
+        "+a = 1\n [db]\n-port = 1\n+port = 2\n"
+    );
+}
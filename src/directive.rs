@@ -0,0 +1,100 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::parse::LineParser;
+use crate::{Entry, OptionProperties, ParseError, ParseOptions};
+
+/// A line starting with '@' (e.g. "@override max_users 50"), with
+/// [ParseOptions::directives](struct.ParseOptions.html#structfield.directives)
+/// set. Directives are kept apart from the options, so that an application
+/// can give them its own meaning (such as overrides applied after all the
+/// options).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Directive {
+    /// The word following the '@' ("override").
+    pub name: String,
+    /// The whitespace-separated words following the name.
+    pub args: Vec<String>,
+    /// The section the directive appears in.
+    pub section: Option<String>,
+    pub line: usize,
+}
+
+/// Like [parse_str_with](fn.parse_str_with.html), but also returns the
+/// [Directive](struct.Directive.html)s, in the order they appear.
+///
+/// # Examples
+///
+/// ```
+/// use configster::ParseOptions;
+///
+/// let mut opts = ParseOptions::default();
+/// opts.directives = true;
+/// let data = "@override max_users 50\nmax_users = 30";
+/// let (config_vec, directives) = configster::parse_str_with_directives(data, &opts).unwrap();
+/// assert_eq!(config_vec.len(), 1);
+/// assert_eq!(directives[0].name, "override");
+/// assert_eq!(directives[0].args, vec!["max_users", "50"]);
+/// ```
+pub fn parse_str_with_directives(
+    s: &str,
+    opts: &ParseOptions,
+) -> Result<(Vec<OptionProperties>, Vec<Directive>), ParseError> {
+    collect_with_directives(LineParser::new(s.lines().map(Ok), opts))
+}
+
+pub(crate) fn collect_with_directives<I, S>(
+    parser: LineParser<'_, '_, I>,
+) -> Result<(Vec<OptionProperties>, Vec<Directive>), ParseError>
+where
+    I: Iterator<Item = Result<S, ParseError>>,
+    S: AsRef<str>,
+{
+    let mut vec = Vec::new();
+    let mut directives = Vec::new();
+    for entry in parser {
+        match entry? {
+            Entry::Option(opt_props) => vec.push(opt_props),
+            Entry::Directive(directive) => directives.push(directive),
+            _ => {}
+        }
+    }
+    Ok((vec, directives))
+}
+
+#[test]
+fn test_parse_str_with_directives() {
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    let opts = ParseOptions {
+        directives: true,
+        ..ParseOptions::default()
+    };
+    let data = "a = 1\n[db]\n  @delete  port \nport = 5432\n@reset";
+    let (config_vec, directives) = parse_str_with_directives(data, &opts).unwrap();
+    assert_eq!(config_vec.len(), 2);
+    assert_eq!(
+        directives,
+        vec![
+            Directive {
+                name: "delete".to_string(),
+                args: vec!["port".to_string()],
+                section: Some("db".to_string()),
+                line: 3,
+            },
+            Directive {
+                name: "reset".to_string(),
+                args: vec![],
+                section: Some("db".to_string()),
+                line: 5,
+            },
+        ]
+    );
+
+    // Without the option, '@' is part of an option name
+    let (config_vec, directives) =
+        parse_str_with_directives("@reset", &ParseOptions::default()).unwrap();
+    assert_eq!(config_vec[0].option, "@reset");
+    assert!(directives.is_empty());
+}
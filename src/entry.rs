@@ -0,0 +1,156 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::{Directive, OptionProperties, ParseError};
+
+/// A line of a configuration, as returned by
+/// [parse_str_entries](fn.parse_str_entries.html) and
+/// [parse_file_entries](fn.parse_file_entries.html). Unlike the other parse
+/// functions, these keep the comments, blank lines and section headers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Entry {
+    /// An option line (or, for a block value, several lines).
+    Option(OptionProperties),
+    /// A section header; holds the section name.
+    Section(String),
+    /// A comment line.
+    Comment(Comment),
+    /// A line that is empty or only holds whitespace.
+    Blank,
+    /// An include directive, with
+    /// [ParseOptions::includes](struct.ParseOptions.html#structfield.includes)
+    /// set.
+    Include(Include),
+    /// A directive ("@name args"), with
+    /// [ParseOptions::directives](struct.ParseOptions.html#structfield.directives)
+    /// set.
+    Directive(Directive),
+    /// A line that isn't a valid option, exactly as it appears in the file,
+    /// with [ParseOptions::raw_invalid_lines](struct.ParseOptions.html#structfield.raw_invalid_lines)
+    /// set.
+    Raw(String),
+}
+
+/// A comment line retained by the entries parse functions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Comment {
+    /// The comment without the leading '#' characters and surrounding
+    /// whitespace (e.g. "set the limit" for "  ## set the limit").
+    pub text: String,
+    /// The line exactly as it appears in the file.
+    pub raw: String,
+    /// The number of leading '#' characters (0 for a block comment); see
+    /// [is_doc](#method.is_doc).
+    pub level: usize,
+}
+
+impl Comment {
+    /// Returns true for a documentation comment, one starting with "##" (or
+    /// more '#' characters), as opposed to an ordinary "#" comment.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use configster::{Entry, ParseOptions};
+    ///
+    /// let entries = configster::parse_str_entries("## The user limit\n# max_users = 10", &ParseOptions::default()).unwrap();
+    /// let docs: Vec<&str> = entries
+    ///     .iter()
+    ///     .filter_map(|e| match e {
+    ///         Entry::Comment(c) if c.is_doc() => Some(c.text.as_str()),
+    ///         _ => None,
+    ///     })
+    ///     .collect();
+    /// assert_eq!(docs, vec!["The user limit"]);
+    /// ```
+    pub fn is_doc(&self) -> bool {
+        self.level >= 2
+    }
+}
+
+/// A block of options under a section header, as returned by
+/// [parse_str_structured](fn.parse_str_structured.html) and
+/// [parse_file_structured](fn.parse_file_structured.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+    /// The name in the header, or `None` for the options before the first
+    /// header.
+    pub name: Option<String>,
+    /// The options of the block, in order.
+    pub options: Vec<OptionProperties>,
+}
+
+/// Groups the options of a sequence of parsed entries into the blocks under
+/// each section header. A header that occurs again starts a new block; a
+/// block is kept even if it is empty, except for the one before the first
+/// header.
+pub(crate) fn collect_sections<I>(entries: I) -> Result<Vec<Section>, ParseError>
+where
+    I: Iterator<Item = Result<Entry, ParseError>>,
+{
+    let mut sections: Vec<Section> = Vec::new();
+    for entry in entries {
+        match entry? {
+            Entry::Section(name) => sections.push(Section {
+                name: Some(name),
+                options: Vec::new(),
+            }),
+            Entry::Option(opt_props) => match sections.last_mut() {
+                Some(section) => section.options.push(opt_props),
+                None => sections.push(Section {
+                    name: None,
+                    options: alloc::vec![opt_props],
+                }),
+            },
+            _ => {}
+        }
+    }
+    Ok(sections)
+}
+
+/// An include directive ("include <path>"); see
+/// [ParseOptions::includes](struct.ParseOptions.html#structfield.includes).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Include {
+    pub kind: IncludeKind,
+    /// The path following the keyword, or for `include_env`, the name of the
+    /// environment variable.
+    pub target: String,
+}
+
+/// The keyword of an [Include](struct.Include.html) directive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IncludeKind {
+    /// `include <path>`; the file must exist.
+    File,
+    /// `include_if_exists <path>`; a missing file is skipped.
+    IfExists,
+    /// `include_env <VAR>`; includes the file named by the environment
+    /// variable, if it is set.
+    Env,
+}
+
+#[test]
+fn test_collect_sections() {
+    use crate::parse::LineParser;
+    use crate::ParseOptions;
+    use alloc::string::ToString;
+
+    let data = "a = 1\nb = 2\n# comment\n[db]\nport = 5432\n[empty]\n\n[db]\nuser = pg";
+    let opts = ParseOptions::default();
+    let sections = collect_sections(LineParser::new(data.lines().map(Ok), &opts)).unwrap();
+    let names: Vec<Option<&str>> = sections.iter().map(|s| s.name.as_deref()).collect();
+    assert_eq!(names, [None, Some("db"), Some("empty"), Some("db")]);
+    let counts: Vec<usize> = sections.iter().map(|s| s.options.len()).collect();
+    assert_eq!(counts, [2, 1, 0, 1]);
+    assert_eq!(sections[3].options[0].section, Some("db".to_string()));
+
+    // No block for the options before the first header if there are none
+    let sections = collect_sections(LineParser::new("[a]\nx".lines().map(Ok), &opts)).unwrap();
+    assert_eq!(sections.len(), 1);
+    let opts = ParseOptions {
+        block_values: true,
+        ..opts
+    };
+    assert!(collect_sections(LineParser::new("s = <<END".lines().map(Ok), &opts)).is_err());
+}
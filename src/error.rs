@@ -0,0 +1,376 @@
+#[cfg(feature = "std")]
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error::Error;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+
+/// The error type for parsing a configuration.
+///
+/// Functions returning an `io::Result` convert a `ParseError` into an
+/// `io::Error`; the original `ParseError` can be recovered with
+/// `io::Error::get_ref()` and `downcast_ref()`.
+#[derive(Debug)]
+pub enum ParseError {
+    /// An I/O error occurred while reading the configuration.
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    /// A block value ("option = <<MARKER") was not closed before the end of
+    /// the input.
+    UnterminatedBlock { option: String, line: usize },
+    /// A block comment (see
+    /// [ParseOptions::block_comments](struct.ParseOptions.html#structfield.block_comments))
+    /// was not closed before the end of the input.
+    UnterminatedBlockComment { line: usize },
+    /// A block comment was opened inside another one.
+    NestedBlockComment { line: usize },
+    /// The indentation of a line has both tabs and spaces, with
+    /// [ParseOptions::reject_mixed_indent](struct.ParseOptions.html#structfield.reject_mixed_indent)
+    /// set.
+    MixedIndent { line: usize },
+    /// The brackets in a value don't match, with
+    /// [ParseOptions::bracket_values](struct.ParseOptions.html#structfield.bracket_values)
+    /// set.
+    UnbalancedBrackets { line: usize },
+    /// An option name contains a character rejected by
+    /// [ParseOptions::option_name_chars](struct.ParseOptions.html#structfield.option_name_chars).
+    InvalidOptionName {
+        option: String,
+        line: usize,
+        col: usize,
+    },
+    /// An option name contains whitespace, with
+    /// [ParseOptions::reject_invalid_options](struct.ParseOptions.html#structfield.reject_invalid_options)
+    /// set.
+    WhitespaceInOptionName {
+        option: String,
+        line: usize,
+        col: usize,
+    },
+    /// An option has more attributes than
+    /// [ParseOptions::max_attributes_per_option](struct.ParseOptions.html#structfield.max_attributes_per_option).
+    TooManyAttributes {
+        option: String,
+        line: usize,
+        max: usize,
+    },
+    /// An option name doesn't start with
+    /// [ParseOptions::required_prefix](struct.ParseOptions.html#structfield.required_prefix).
+    MissingPrefix {
+        option: String,
+        prefix: String,
+        line: usize,
+    },
+    /// A value of an option that is
+    /// [Kind::Numbers](enum.Kind.html#variant.Numbers) in the schema isn't a
+    /// number; `index` is its position in the list (0 for the primary value).
+    NotANumber {
+        option: String,
+        value: String,
+        index: usize,
+        line: usize,
+    },
+    /// A value refers to a name that is neither an option nor (if enabled)
+    /// an environment variable.
+    UndefinedReference { option: String, name: String },
+    /// A value refers to the option it belongs to, which isn't defined before
+    /// it. The options don't know their lines, so `line` is the position of
+    /// the option in the vector, counting from 1.
+    SelfReference { option: String, line: usize },
+    /// A file includes itself, directly or through other included files.
+    #[cfg(feature = "std")]
+    IncludeCycle { path: PathBuf },
+    /// The file at `path` is included through more levels than
+    /// [ParseOptions::max_include_depth](struct.ParseOptions.html#structfield.max_include_depth).
+    #[cfg(feature = "std")]
+    IncludeDepth { path: PathBuf, max: usize },
+    /// The pattern of an include directive isn't a valid glob pattern (see
+    /// [ParseOptions::includes](struct.ParseOptions.html#structfield.includes));
+    /// `msg` says why. Needs the `glob` feature.
+    #[cfg(feature = "glob")]
+    InvalidGlob { pattern: String, msg: &'static str },
+    /// The path an option names doesn't exist, with
+    /// [ParseOptions::require_paths](struct.ParseOptions.html#structfield.require_paths)
+    /// set.
+    #[cfg(feature = "std")]
+    MissingPath {
+        option: String,
+        path: PathBuf,
+        line: usize,
+    },
+    /// The path of the configuration file is a directory.
+    #[cfg(feature = "std")]
+    PathIsDirectory { path: PathBuf },
+    /// The file has bytes that aren't valid in its encoding (the name of the
+    /// encoding, e.g. "windows-1252"), with
+    /// [Unmappable::Error](enum.Unmappable.html#variant.Error).
+    #[cfg(feature = "encoding")]
+    Decode { encoding: &'static str },
+    /// An error that occurred while parsing the file at `path`.
+    #[cfg(feature = "std")]
+    InFile {
+        path: PathBuf,
+        error: Box<ParseError>,
+    },
+}
+
+impl ParseError {
+    /// Returns the line number the error refers to, if any.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            ParseError::UnterminatedBlock { line, .. }
+            | ParseError::UnterminatedBlockComment { line }
+            | ParseError::NestedBlockComment { line }
+            | ParseError::MixedIndent { line }
+            | ParseError::UnbalancedBrackets { line }
+            | ParseError::InvalidOptionName { line, .. }
+            | ParseError::WhitespaceInOptionName { line, .. }
+            | ParseError::TooManyAttributes { line, .. }
+            | ParseError::MissingPrefix { line, .. }
+            | ParseError::NotANumber { line, .. } => Some(*line),
+            #[cfg(feature = "std")]
+            ParseError::MissingPath { line, .. } => Some(*line),
+            #[cfg(feature = "std")]
+            ParseError::InFile { error, .. } => error.line(),
+            _ => None,
+        }
+    }
+
+    /// Returns the column (counted in characters, starting at 1) the error
+    /// refers to, if any.
+    pub fn col(&self) -> Option<usize> {
+        match self {
+            ParseError::InvalidOptionName { col, .. }
+            | ParseError::WhitespaceInOptionName { col, .. } => Some(*col),
+            #[cfg(feature = "std")]
+            ParseError::InFile { error, .. } => error.col(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ParseError {
+    /// Wraps the error with the path of the file being parsed.
+    pub(crate) fn in_file(self, path: impl Into<PathBuf>) -> Self {
+        match self {
+            // The error already names the file
+            ParseError::PathIsDirectory { .. } => self,
+            _ => ParseError::InFile {
+                path: path.into(),
+                error: Box::new(self),
+            },
+        }
+    }
+
+    pub(crate) fn io_kind(&self) -> io::ErrorKind {
+        match self {
+            ParseError::Io(e) => e.kind(),
+            ParseError::InFile { error, .. } => error.io_kind(),
+            ParseError::PathIsDirectory { .. } => io::ErrorKind::InvalidInput,
+            _ => io::ErrorKind::InvalidData,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            ParseError::Io(e) => write!(f, "{}", e),
+            ParseError::UnterminatedBlock { option, line } => write!(
+                f,
+                "line {}: unterminated block value for '{}'",
+                line, option
+            ),
+            ParseError::UnterminatedBlockComment { line } => {
+                write!(f, "line {}: unterminated block comment", line)
+            }
+            ParseError::NestedBlockComment { line } => {
+                write!(f, "line {}: nested block comment", line)
+            }
+            ParseError::MixedIndent { line } => {
+                write!(f, "line {}: indentation mixes tabs and spaces", line)
+            }
+            ParseError::UnbalancedBrackets { line } => {
+                write!(f, "line {}: unbalanced brackets in the value", line)
+            }
+            ParseError::InvalidOptionName { option, line, col } => write!(
+                f,
+                "line {}, col {}: invalid character in option name '{}'",
+                line, col, option
+            ),
+            ParseError::WhitespaceInOptionName { option, line, col } => write!(
+                f,
+                "line {}, col {}: whitespace in option name '{}'",
+                line, col, option
+            ),
+            ParseError::TooManyAttributes { option, line, max } => write!(
+                f,
+                "line {}: more than {} attributes for '{}'",
+                line, max, option
+            ),
+            ParseError::MissingPrefix {
+                option,
+                prefix,
+                line,
+            } => write!(
+                f,
+                "line {}: option '{}' doesn't start with '{}'",
+                line, option, prefix
+            ),
+            ParseError::NotANumber {
+                option,
+                value,
+                line,
+                ..
+            } => write!(
+                f,
+                "line {}: '{}' in the value of '{}' is not a number",
+                line, value, option
+            ),
+            ParseError::UndefinedReference { option, name } => {
+                write!(f, "option '{}' refers to undefined '{}'", option, name)
+            }
+            ParseError::SelfReference { option, line } => write!(
+                f,
+                "option {}: '{}' refers to itself, with no earlier definition",
+                line, option
+            ),
+            #[cfg(feature = "std")]
+            ParseError::IncludeCycle { path } => {
+                write!(f, "{}: file includes itself", path.display())
+            }
+            #[cfg(feature = "std")]
+            ParseError::IncludeDepth { path, max } => write!(
+                f,
+                "{}: includes are nested more than {} deep",
+                path.display(),
+                max
+            ),
+            #[cfg(feature = "glob")]
+            ParseError::InvalidGlob { pattern, msg } => {
+                write!(f, "invalid include pattern '{}': {}", pattern, msg)
+            }
+            #[cfg(feature = "std")]
+            ParseError::MissingPath { option, path, line } => write!(
+                f,
+                "line {}: the path of '{}' doesn't exist: {}",
+                line,
+                option,
+                path.display()
+            ),
+            #[cfg(feature = "std")]
+            ParseError::PathIsDirectory { path } => {
+                write!(f, "{}: is a directory", path.display())
+            }
+            #[cfg(feature = "encoding")]
+            ParseError::Decode { encoding } => write!(f, "the file isn't valid {}", encoding),
+            #[cfg(feature = "std")]
+            ParseError::InFile { path, error } => write!(f, "{}: {}", path.display(), error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseError::Io(e) => Some(e),
+            ParseError::InFile { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// The error type for converting a [Value](struct.Value.html) to another
+/// type, e.g. with [Value::as_duration](struct.Value.html#method.as_duration).
+/// Each variant holds the (trimmed) primary value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseValueError {
+    /// The value doesn't start with a number.
+    InvalidNumber { value: String },
+    /// The unit after the number isn't one of those allowed.
+    UnknownUnit { value: String, unit: String },
+    /// The value is too large for the type it is converted to.
+    Overflow { value: String },
+}
+
+impl fmt::Display for ParseValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseValueError::InvalidNumber { value } => write!(f, "'{}' is not a number", value),
+            ParseValueError::UnknownUnit { value, unit } => {
+                write!(f, "unknown unit '{}' in '{}'", unit, value)
+            }
+            ParseValueError::Overflow { value } => write!(f, "'{}' is too large", value),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for ParseValueError {}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for ParseError {
+    fn from(e: io::Error) -> Self {
+        ParseError::Io(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<ParseError> for io::Error {
+    fn from(e: ParseError) -> Self {
+        match e {
+            ParseError::Io(e) => e,
+            e => io::Error::new(e.io_kind(), e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_parse_error_into_io_error() {
+    let err = io::Error::from(ParseError::UnterminatedBlock {
+        option: "script".to_string(),
+        line: 3,
+    });
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(
+        err.to_string(),
+        "line 3: unterminated block value for 'script'"
+    );
+    assert!(err
+        .get_ref()
+        .unwrap()
+        .downcast_ref::<ParseError>()
+        .is_some());
+
+    let err = io::Error::from(ParseError::Io(io::Error::from(io::ErrorKind::NotFound)));
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_parse_error_in_file() {
+    let err = ParseError::UnterminatedBlock {
+        option: "script".to_string(),
+        line: 7,
+    }
+    .in_file("/etc/app.conf");
+    assert_eq!(
+        err.to_string(),
+        "/etc/app.conf: line 7: unterminated block value for 'script'"
+    );
+    assert!(err.source().is_some());
+
+    let err = io::Error::from(
+        ParseError::from(io::Error::from(io::ErrorKind::NotFound)).in_file("a.conf"),
+    );
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(err.to_string().starts_with("a.conf: "));
+}
@@ -0,0 +1,1053 @@
+use std::env;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+
+use crate::directive::collect_with_directives;
+use crate::entry::collect_sections;
+use crate::parse::{only_options, LineParser};
+use crate::visit::visit;
+use crate::warning::collect_with_warnings;
+use crate::write::format_str;
+#[cfg(feature = "encoding")]
+use crate::Unmappable;
+use crate::{
+    merge, parse_str, Directive, Entry, Include, IncludeKind, OptionProperties, ParseError,
+    ParseOptions, Section, Visitor, Warning,
+};
+
+/// Parses a configuration file. The second parameter sets the delimiter for the
+/// attribute list of the primary value. The return value is an [OptionProperties](struct.OptionProperties.html)
+/// type vector wrapped in an io::Result type. Details about the configuration file format are in the project's
+/// [README.md](https://github.com/theimpossibleastronaut/configster/blob/trunk/README.md).
+///
+/// # Examples
+///
+/// Accessing the Parsed Data:
+///
+/// ```
+/// use std::io;
+///
+/// fn main() -> Result<(), io::Error> {
+///
+///     let config_vec = configster::parse_file("./config_test.conf", ',')?;
+///
+///     for i in &config_vec {
+///         println!("Option:'{}' | value '{}'", i.option, i.value.primary);
+///
+///         for j in &i.value.attributes {
+///             println!("attr:'{}`", j);
+///         }
+///         println!();
+///     }
+///     Ok(())
+/// }
+/// ```
+///
+/// As is usual for command line tools, the filename "-" reads the configuration
+/// from the standard input, with "<stdin>" in place of the path in errors.
+/// This holds for all the functions taking a filename.
+///
+/// Errors are reported with the path of the file prepended, e.g.
+/// "/etc/app.conf: line 7: ...". The underlying
+/// [ParseError](enum.ParseError.html) is available through `io::Error::get_ref()`.
+#[inline]
+pub fn parse_file(filename: &str, attr_delimit_char: char) -> io::Result<Vec<OptionProperties>> {
+    parse_file_with(filename, &ParseOptions::with_delimiter(attr_delimit_char))
+}
+
+/// Like [parse_file_with](fn.parse_file_with.html), for a file in an encoding
+/// other than UTF-8 (e.g. `encoding_rs::WINDOWS_1252`); the file is decoded
+/// before it is parsed, with `unmappable` setting what happens to invalid
+/// bytes. A byte order mark, if any, takes precedence over `encoding`.
+/// Include directives aren't followed. Needs the `encoding` feature.
+///
+/// # Examples
+///
+/// ```
+/// use configster::{ParseOptions, Unmappable};
+///
+/// let config_vec = configster::parse_file_encoded(
+///     "./config_test.conf",
+///     &ParseOptions::default(),
+///     configster::encoding_rs::WINDOWS_1252,
+///     Unmappable::Error,
+/// )
+/// .unwrap();
+/// assert_eq!(config_vec[1].option, "max_users");
+/// ```
+#[cfg(feature = "encoding")]
+pub fn parse_file_encoded(
+    filename: &str,
+    opts: &ParseOptions,
+    encoding: &'static encoding_rs::Encoding,
+    unmappable: Unmappable,
+) -> io::Result<Vec<OptionProperties>> {
+    let parsed = open_file(Path::new(filename))
+        .and_then(|mut file| {
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes)?;
+            let (s, used, had_errors) = encoding.decode(&bytes);
+            if had_errors && unmappable == Unmappable::Error {
+                return Err(ParseError::Decode {
+                    encoding: used.name(),
+                });
+            }
+            read_options(s.as_bytes(), opts)
+        })
+        .map_err(|e| e.in_file(source_name(filename)))?;
+    Ok(parsed)
+}
+
+/// Like [parse_file](fn.parse_file.html), but the attribute delimiter is a string,
+/// which may be longer than one character (e.g. "::").
+///
+/// # Examples
+///
+/// ```
+/// let config_vec = configster::parse_file_str_delim("./config_test.conf", ", ").unwrap();
+/// assert_eq!(config_vec[0].value.attributes, vec!["light", "shiny"]);
+/// ```
+pub fn parse_file_str_delim(
+    filename: &str,
+    attr_delimit_str: &str,
+) -> io::Result<Vec<OptionProperties>> {
+    parse_file_with(filename, &ParseOptions::with_delimiter(attr_delimit_str))
+}
+
+/// Like [parse_file](fn.parse_file.html), with the parsing behavior set by
+/// [ParseOptions](struct.ParseOptions.html). This function (and the ones built
+/// on it) follows include directives if
+/// [ParseOptions::includes](struct.ParseOptions.html#structfield.includes) is
+/// set.
+///
+/// # Examples
+///
+/// ```
+/// use configster::ParseOptions;
+///
+/// let mut opts = ParseOptions::default();
+/// opts.includes = true;
+/// // config_test.conf has no include directives
+/// let config_vec = configster::parse_file_with("./config_test.conf", &opts).unwrap();
+/// assert_eq!(config_vec[1].option, "max_users");
+/// ```
+pub fn parse_file_with(filename: &str, opts: &ParseOptions) -> io::Result<Vec<OptionProperties>> {
+    Ok(read_file_options(
+        Path::new(filename),
+        opts,
+        &mut Vec::new(),
+        &|name| env::var_os(name),
+    )?)
+}
+
+/// The name of the standard input in errors
+const STDIN_NAME: &str = "<stdin>";
+
+/// Like [parse_file](fn.parse_file.html), but the attribute delimiter is
+/// whichever of ',' and ';' occurs more often in the values of the file (','
+/// if neither does). Returns the chosen delimiter with the options.
+///
+/// # Examples
+///
+/// ```
+/// let (delim, config_vec) = configster::parse_file_autodelim("./config_test.conf").unwrap();
+/// assert_eq!(delim, ',');
+/// assert_eq!(config_vec[0].value.attributes, vec!["light", "shiny"]);
+/// ```
+pub fn parse_file_autodelim(filename: &str) -> io::Result<(char, Vec<OptionProperties>)> {
+    let parsed = read_file(Path::new(filename))
+        .and_then(|s| {
+            let delim = detect_delimiter(&s);
+            Ok((delim, parse_str(&s, delim)?))
+        })
+        .map_err(|e| e.in_file(source_name(filename)))?;
+    Ok(parsed)
+}
+
+/// Counts the candidate delimiters in the values (the text after the first
+/// '=' of each option line) and returns the most frequent one.
+fn detect_delimiter(s: &str) -> char {
+    const CANDIDATES: [char; 2] = [',', ';'];
+    let mut counts = [0; CANDIDATES.len()];
+    for line in s.lines().map(str::trim) {
+        if line.starts_with('#') || line.starts_with('[') {
+            continue;
+        }
+        if let Some((_, value)) = line.split_once('=') {
+            for c in value.chars() {
+                if let Some(i) = CANDIDATES.iter().position(|&d| d == c) {
+                    counts[i] += 1;
+                }
+            }
+        }
+    }
+    // The first candidate wins a tie
+    let mut best = 0;
+    for i in 1..CANDIDATES.len() {
+        if counts[i] > counts[best] {
+            best = i;
+        }
+    }
+    CANDIDATES[best]
+}
+
+/// Like [parse_file](fn.parse_file.html), but stops reading the file after the
+/// first `n` options (e.g. to check a "version" option at the top of a large
+/// file). Include directives aren't followed.
+///
+/// # Examples
+///
+/// ```
+/// let config_vec = configster::parse_file_take("./config_test.conf", ',', 2).unwrap();
+/// assert_eq!(config_vec.len(), 2);
+/// assert_eq!(config_vec[1].option, "max_users");
+/// ```
+pub fn parse_file_take(
+    filename: &str,
+    attr_delimit_char: char,
+    n: usize,
+) -> io::Result<Vec<OptionProperties>> {
+    let opts = ParseOptions::with_delimiter(attr_delimit_char);
+    let vec = open_file(Path::new(filename))
+        .and_then(|file| only_options(read_lines(file, &opts)).take(n).collect())
+        .map_err(|e| e.in_file(source_name(filename)))?;
+    Ok(vec)
+}
+
+/// Like [parse_file_with](fn.parse_file_with.html), but returns every line as an
+/// [Entry](enum.Entry.html), including comments, blank lines and section
+/// headers.
+pub fn parse_file_entries(filename: &str, opts: &ParseOptions) -> io::Result<Vec<Entry>> {
+    let entries = open_file(Path::new(filename))
+        .and_then(|file| read_lines(file, opts).collect())
+        .map_err(|e| e.in_file(source_name(filename)))?;
+    Ok(entries)
+}
+
+/// Like [parse_file_with](fn.parse_file_with.html), but returns the options
+/// grouped into the blocks under each section header, in the order they
+/// appear, instead of a flat vector. Include directives aren't followed.
+///
+/// # Examples
+///
+/// ```
+/// use configster::ParseOptions;
+///
+/// let sections = configster::parse_file_structured("./config_test.conf", &ParseOptions::default()).unwrap();
+/// // config_test.conf has no section headers
+/// assert_eq!(sections.len(), 1);
+/// assert_eq!(sections[0].name, None);
+/// assert_eq!(sections[0].options[1].option, "max_users");
+/// ```
+pub fn parse_file_structured(filename: &str, opts: &ParseOptions) -> io::Result<Vec<Section>> {
+    let sections = open_file(Path::new(filename))
+        .and_then(|file| collect_sections(read_lines(file, opts)))
+        .map_err(|e| e.in_file(source_name(filename)))?;
+    Ok(sections)
+}
+
+/// Like [parse_file_with](fn.parse_file_with.html), but also returns a list of
+/// warnings; see [parse_str_with_warnings](fn.parse_str_with_warnings.html).
+pub fn parse_file_with_warnings(
+    filename: &str,
+    opts: &ParseOptions,
+) -> io::Result<(Vec<OptionProperties>, Vec<Warning>)> {
+    let parsed = open_file(Path::new(filename))
+        .and_then(|file| collect_with_warnings(read_lines(file, opts)))
+        .map_err(|e| e.in_file(source_name(filename)))?;
+    Ok(parsed)
+}
+
+/// Like [parse_file_with](fn.parse_file_with.html), but also returns the
+/// directives; see [parse_str_with_directives](fn.parse_str_with_directives.html).
+/// Include directives aren't followed.
+pub fn parse_file_with_directives(
+    filename: &str,
+    opts: &ParseOptions,
+) -> io::Result<(Vec<OptionProperties>, Vec<Directive>)> {
+    let parsed = open_file(Path::new(filename))
+        .and_then(|file| collect_with_directives(read_lines(file, opts)))
+        .map_err(|e| e.in_file(source_name(filename)))?;
+    Ok(parsed)
+}
+
+/// Parses a configuration file, passing each option to the visitor along with
+/// its line number; see [visit_str](fn.visit_str.html).
+pub fn visit_file<V: Visitor + ?Sized>(
+    filename: &str,
+    opts: &ParseOptions,
+    visitor: &mut V,
+) -> io::Result<()> {
+    open_file(Path::new(filename))
+        .and_then(|file| visit(read_lines(file, opts), visitor))
+        .map_err(|e| e.in_file(source_name(filename)))?;
+    Ok(())
+}
+
+/// Reads a configuration file and returns it reformatted: option lines are
+/// written in a canonical form, with single spaces around the separator and
+/// after each attribute delimiter and no trailing whitespace, while comment
+/// lines and blank lines are kept as they are. Formatting an already
+/// formatted file doesn't change it. Inline and block comments (see
+/// [ParseOptions](struct.ParseOptions.html)) aren't kept.
+///
+/// # Examples
+///
+/// ```
+/// use configster::ParseOptions;
+///
+/// let formatted = configster::format_file("./config_test.conf", &ParseOptions::default()).unwrap();
+/// assert!(formatted.starts_with("option = Blue, light, shiny\n"));
+/// ```
+pub fn format_file(filename: &str, opts: &ParseOptions) -> io::Result<String> {
+    let formatted = read_file(Path::new(filename))
+        .and_then(|s| format_str(&s, opts))
+        .map_err(|e| e.in_file(source_name(filename)))?;
+    Ok(formatted)
+}
+
+/// Checks that a configuration file parses, without keeping the options.
+/// Unlike the parse functions, parsing goes on after an error, so that every
+/// error in the file is returned (each with the path of the file). Include
+/// directives aren't followed.
+///
+/// # Examples
+///
+/// ```
+/// use configster::ParseOptions;
+///
+/// assert!(configster::validate_file("./config_test.conf", &ParseOptions::default()).is_ok());
+///
+/// let mut opts = ParseOptions::default();
+/// opts.reject_invalid_options = true;
+/// let errors = configster::validate_file("./config_test.conf", &opts).unwrap_err();
+/// assert_eq!(errors.len(), 1);
+/// assert_eq!(errors[0].line(), Some(8));
+/// ```
+pub fn validate_file(filename: &str, opts: &ParseOptions) -> Result<(), Vec<ParseError>> {
+    let file =
+        open_file(Path::new(filename)).map_err(|e| vec![e.in_file(source_name(filename))])?;
+    let mut errors = Vec::new();
+    for entry in read_lines(file, opts) {
+        if let Err(e) = entry {
+            // Reading can't go on after an I/O error
+            let stop = matches!(e, ParseError::Io(_));
+            errors.push(e.in_file(source_name(filename)));
+            if stop {
+                break;
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Parses the configuration in `default_str` (e.g. a default configuration
+/// embedded with `include_str!`) and merges the user's configuration file on
+/// top of it with [merge](fn.merge.html). A missing user file isn't an error; the
+/// defaults are returned as they are.
+///
+/// # Examples
+///
+/// ```
+/// let config_vec = configster::parse_with_default(
+///     "max_users = 10\nDelayOff",
+///     "./config_test.conf",
+///     ',',
+/// )
+/// .unwrap();
+/// assert_eq!(config_vec[0].value.primary, "30");
+/// ```
+pub fn parse_with_default(
+    default_str: &str,
+    user_path: &str,
+    attr_delimit_char: char,
+) -> io::Result<Vec<OptionProperties>> {
+    let defaults = parse_str(default_str, attr_delimit_char)?;
+    match parse_file(user_path, attr_delimit_char) {
+        Ok(user) => Ok(merge(defaults, user)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(defaults),
+        Err(e) => Err(e),
+    }
+}
+
+/// Parses configuration data from any buffered reader. This is what
+/// [parse_file](fn.parse_file.html) uses after opening the file; the parameters
+/// and the return value are the same.
+///
+/// # Examples
+///
+/// ```
+/// let data = "option = Blue, light, shiny\nDelayOff\n";
+/// let config_vec = configster::parse_reader(data.as_bytes(), ',').unwrap();
+/// assert_eq!(config_vec[0].value.attributes, vec!["light", "shiny"]);
+/// ```
+pub fn parse_reader<R: BufRead>(
+    reader: R,
+    attr_delimit_char: char,
+) -> io::Result<Vec<OptionProperties>> {
+    parse_reader_with(reader, &ParseOptions::with_delimiter(attr_delimit_char))
+}
+
+/// Like [parse_reader](fn.parse_reader.html), with the parsing behavior set by
+/// [ParseOptions](struct.ParseOptions.html).
+///
+/// # Examples
+///
+/// ```
+/// use configster::ParseOptions;
+///
+/// let mut opts = ParseOptions::default();
+/// opts.block_values = true;
+/// let data = "script = <<END\necho one\necho two\nEND\n";
+/// let config_vec = configster::parse_reader_with(data.as_bytes(), &opts).unwrap();
+/// assert_eq!(config_vec[0].value.primary, "echo one\necho two");
+/// ```
+pub fn parse_reader_with<R: BufRead>(
+    reader: R,
+    opts: &ParseOptions,
+) -> io::Result<Vec<OptionProperties>> {
+    Ok(read_options(reader, opts)?)
+}
+
+/// Parses the options of the file at `path`, replacing each include directive
+/// with the options of the included file. `stack` holds the files being
+/// parsed, to detect include cycles, and `env` looks up the variables of the
+/// `include_env` directives.
+fn read_file_options(
+    path: &Path,
+    opts: &ParseOptions,
+    stack: &mut Vec<PathBuf>,
+    env: &dyn Fn(&str) -> Option<OsString>,
+) -> Result<Vec<OptionProperties>, ParseError> {
+    let name = source_name(path);
+    let file = open_file(path).map_err(|e| e.in_file(name))?;
+    let canonical = match path == Path::new("-") {
+        true => PathBuf::from(STDIN_NAME),
+        false => path
+            .canonicalize()
+            .map_err(|e| ParseError::from(e).in_file(path))?,
+    };
+    if stack.contains(&canonical) {
+        return Err(ParseError::IncludeCycle {
+            path: path.to_path_buf(),
+        });
+    }
+    if stack.len() > opts.max_include_depth {
+        return Err(ParseError::IncludeDepth {
+            path: path.to_path_buf(),
+            max: opts.max_include_depth,
+        });
+    }
+    stack.push(canonical);
+    let vec = read_with_includes(file, name, opts, stack, env)?;
+    stack.pop();
+    Ok(vec)
+}
+
+/// Opens a configuration file, checking that it isn't a directory (which
+/// would otherwise only fail on the first read). The filename "-" opens the
+/// standard input.
+fn open_file(path: &Path) -> Result<Box<dyn BufRead>, ParseError> {
+    if path == Path::new("-") {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    let file = File::open(path)?;
+    if file.metadata()?.is_dir() {
+        return Err(ParseError::PathIsDirectory {
+            path: path.to_path_buf(),
+        });
+    }
+    Ok(Box::new(BufReader::new(file)))
+}
+
+/// The name of a file in errors, "<stdin>" for the standard input.
+fn source_name<P: AsRef<Path> + ?Sized>(path: &P) -> &Path {
+    match path.as_ref() {
+        p if p == Path::new("-") => Path::new(STDIN_NAME),
+        p => p,
+    }
+}
+
+/// Reads a configuration file into a string, like `open_file`.
+fn read_file(path: &Path) -> Result<String, ParseError> {
+    let mut s = String::new();
+    open_file(path)?.read_to_string(&mut s)?;
+    Ok(s)
+}
+
+/// Parses the options from a reader, following the include directives;
+/// `path` names the source in errors and is where relative includes start.
+fn read_with_includes<R: BufRead>(
+    reader: R,
+    path: &Path,
+    opts: &ParseOptions,
+    stack: &mut Vec<PathBuf>,
+    env: &dyn Fn(&str) -> Option<OsString>,
+) -> Result<Vec<OptionProperties>, ParseError> {
+    let mut vec = Vec::new();
+    let mut parser = read_lines(reader, opts);
+    while let Some(entry) = parser.next() {
+        match entry.map_err(|e| e.in_file(path))? {
+            Entry::Option(mut opt_props) => {
+                if opts.resolve_paths {
+                    resolve_option_path(&mut opt_props, path, opts, parser.line())
+                        .map_err(|e| e.in_file(path))?;
+                }
+                vec.push(opt_props);
+            }
+            Entry::Include(include) => {
+                let target = match include_path(path, &include, env) {
+                    Some(target) => target,
+                    None => continue,
+                };
+                for target in include_targets(target).map_err(|e| e.in_file(path))? {
+                    // Only the target itself may be missing; errors from
+                    // reading it (including its own includes) aren't skipped
+                    if include.kind == IncludeKind::IfExists && !target.exists() {
+                        continue;
+                    }
+                    vec.extend(read_file_options(&target, opts, stack, env)?);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(vec)
+}
+
+/// Replaces the primary value of a path option with the absolute path it
+/// names, relative to the directory of the file at `path`.
+fn resolve_option_path(
+    opt_props: &mut OptionProperties,
+    path: &Path,
+    opts: &ParseOptions,
+    line: usize,
+) -> Result<(), ParseError> {
+    let value = &opt_props.value;
+    if value.primary.is_empty() || !opts.path_options.contains(&opt_props.option.as_str()) {
+        return Ok(());
+    }
+    let target = value.resolve_path(path.parent().unwrap_or_else(|| Path::new("")));
+    let resolved = match target.canonicalize() {
+        Ok(resolved) => resolved,
+        Err(e) if opts.require_paths && e.kind() == io::ErrorKind::NotFound => {
+            return Err(ParseError::MissingPath {
+                option: opt_props.option.clone(),
+                path: target,
+                line,
+            });
+        }
+        Err(e) if opts.require_paths => return Err(e.into()),
+        Err(_) => normalize_path(&env::current_dir()?.join(target)),
+    };
+    opt_props.value.primary = resolved.to_string_lossy().into_owned();
+    Ok(())
+}
+
+/// Removes the "." and ".." components of a path without looking at the file
+/// system (so ".." after a symbolic link goes back to where the link is).
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// Returns the files to include for a target path: the path itself or, with
+/// the `glob` feature, the files matching a pattern ("conf.d/*.conf") in
+/// sorted order. A pattern matching no files includes nothing.
+fn include_targets(target: PathBuf) -> Result<Vec<PathBuf>, ParseError> {
+    #[cfg(feature = "glob")]
+    {
+        if let Some(pattern) = target.to_str().filter(|t| t.contains(&['*', '?', '['][..])) {
+            let paths = glob::glob(pattern).map_err(|e| ParseError::InvalidGlob {
+                pattern: pattern.to_string(),
+                msg: e.msg,
+            })?;
+            let mut targets = paths
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| ParseError::from(io::Error::from(e)))?;
+            targets.sort();
+            return Ok(targets);
+        }
+    }
+    Ok(vec![target])
+}
+
+/// Returns the path of the file to include, relative to the directory of the
+/// including file; `None` for an `include_env` directive whose variable isn't
+/// set (or is empty) in `env`.
+fn include_path(
+    including: &Path,
+    include: &Include,
+    env: &dyn Fn(&str) -> Option<OsString>,
+) -> Option<PathBuf> {
+    let target = match include.kind {
+        IncludeKind::Env => PathBuf::from(env(&include.target).filter(|v| !v.is_empty())?),
+        _ => PathBuf::from(&include.target),
+    };
+    Some(
+        including
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(target),
+    )
+}
+
+fn read_options<R: BufRead>(
+    reader: R,
+    opts: &ParseOptions,
+) -> Result<Vec<OptionProperties>, ParseError> {
+    only_options(read_lines(reader, opts)).collect()
+}
+
+fn read_lines<'o, 'a, R: BufRead>(
+    reader: R,
+    opts: &'o ParseOptions<'a>,
+) -> LineParser<'o, 'a, impl Iterator<Item = Result<String, ParseError>>> {
+    LineParser::new(reader.lines().map(|l| l.map_err(ParseError::from)), opts)
+}
+
+/// An empty temporary directory for the fixtures of a test, named after the
+/// test and the process so that tests running at the same time don't share
+/// files. It is removed (with its contents) when dropped.
+#[cfg(test)]
+struct TestDir(PathBuf);
+
+#[cfg(test)]
+impl TestDir {
+    fn new(test: &str) -> Self {
+        let name = format!("configster_{}_{}", std::process::id(), test);
+        let dir = std::env::temp_dir().join(name);
+        // Left over from an earlier run that didn't finish
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        TestDir(dir)
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn test_parse_file() {
+    assert_eq!(
+        parse_file("./config_test.conf", ',').unwrap(),
+        config![
+            "option" => "Blue", ["light", "shiny"];
+            "max_users" => "30";
+            "DelayOff" => "";
+            "InvalidOption_on_Line8" => "";
+        ]
+    );
+}
+
+#[test]
+fn test_parse_reader_heredoc() {
+    let data = "before = 1\nscript = <<END\n  cd /tmp\n\necho done\nEND\nafter = 2\n";
+    let script = OptionProperties::new(
+        "script".to_string(),
+        "  cd /tmp\n\necho done".to_string(),
+        vec![],
+    );
+    let opts = ParseOptions {
+        block_values: true,
+        ..ParseOptions::default()
+    };
+    let config_vec = parse_reader_with(data.as_bytes(), &opts).unwrap();
+    assert_eq!(config_vec.len(), 3);
+    assert_eq!(config_vec[1], script);
+    assert_eq!(config_vec[2].option, "after");
+
+    // An empty block
+    let config_vec = parse_reader_with("script = <<EOF\nEOF".as_bytes(), &opts).unwrap();
+    assert_eq!(config_vec[0].value.primary, "");
+
+    // The end marker is missing
+    let err = parse_reader_with("script = <<END\necho\n".as_bytes(), &opts).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    // Without block_values, the marker is the value
+    let config_vec = parse_reader(data.as_bytes(), ',').unwrap();
+    assert_eq!(config_vec[1].value.primary, "<<END");
+    assert_eq!(config_vec[2].option, "InvalidOption_on_Line3");
+}
+
+#[test]
+fn test_final_line_without_newline() {
+    for data in ["a = 1\nlast = 2, x", "a = 1\nlast = 2, x\n"] {
+        let from_reader = parse_reader(data.as_bytes(), ',').unwrap();
+        assert_eq!(from_reader.len(), 2);
+        assert_eq!(from_reader[1].option, "last");
+        assert_eq!(from_reader[1].value.attributes, vec!["x"]);
+        assert_eq!(from_reader, parse_str(data, ',').unwrap());
+    }
+
+    // A flag option, and a block value closed on the last line
+    let config_vec = parse_reader("a = 1\nDelayOff".as_bytes(), ',').unwrap();
+    assert_eq!(config_vec[1].option, "DelayOff");
+    let opts = ParseOptions {
+        block_values: true,
+        ..ParseOptions::default()
+    };
+    let config_vec = parse_reader_with("s = <<END\necho\nEND".as_bytes(), &opts).unwrap();
+    assert_eq!(config_vec[0].value.primary, "echo");
+
+    let dir = TestDir::new("no_newline");
+    let path = dir.join("no_newline.conf");
+    std::fs::write(&path, "max_users = 30\nDelayOff").unwrap();
+    let config_vec = parse_file(path.to_str().unwrap(), ',').unwrap();
+    assert_eq!(config_vec.len(), 2);
+    assert_eq!(config_vec[1].option, "DelayOff");
+}
+
+#[test]
+fn test_parse_file_directory() {
+    let tmp = TestDir::new("parse_file_directory");
+    let dir = tmp.join("dir.conf");
+    std::fs::create_dir(&dir).unwrap();
+    let err = parse_file(dir.to_str().unwrap(), ',').unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    let err = err.into_inner().unwrap().downcast::<ParseError>().unwrap();
+    assert!(matches!(*err, ParseError::PathIsDirectory { ref path } if *path == dir));
+    assert_eq!(
+        err.to_string(),
+        format!("{}: is a directory", dir.display())
+    );
+    let errors = validate_file(dir.to_str().unwrap(), &ParseOptions::default()).unwrap_err();
+    assert!(matches!(errors[0], ParseError::PathIsDirectory { .. }));
+    assert!(format_file(dir.to_str().unwrap(), &ParseOptions::default()).is_err());
+}
+
+#[cfg(feature = "encoding")]
+#[test]
+fn test_parse_file_encoded() {
+    let dir = TestDir::new("parse_file_encoded");
+    let path = dir.join("latin1.conf");
+    // "café = crème, brûlée" in Windows-1252
+    std::fs::write(&path, b"caf\xe9 = cr\xe8me, br\xfbl\xe9e\nx = \x81").unwrap();
+    let filename = path.to_str().unwrap();
+    let opts = ParseOptions::default();
+    let config_vec = parse_file_encoded(
+        filename,
+        &opts,
+        encoding_rs::WINDOWS_1252,
+        Unmappable::Error,
+    )
+    .unwrap();
+    assert_eq!(config_vec[0].option, "café");
+    assert_eq!(config_vec[0].value.primary, "crème");
+    assert_eq!(config_vec[0].value.attributes, vec!["brûlée"]);
+
+    // Not valid UTF-8
+    let err =
+        parse_file_encoded(filename, &opts, encoding_rs::UTF_8, Unmappable::Error).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.to_string().ends_with("the file isn't valid UTF-8"));
+    let config_vec =
+        parse_file_encoded(filename, &opts, encoding_rs::UTF_8, Unmappable::Replace).unwrap();
+    assert_eq!(config_vec[0].option, "caf\u{fffd}");
+    assert_eq!(config_vec[1].value.primary, "\u{fffd}");
+}
+
+#[test]
+fn test_parse_file_take() {
+    let all = parse_file("./config_test.conf", ',').unwrap();
+    assert_eq!(
+        parse_file_take("./config_test.conf", ',', 0).unwrap(),
+        vec![]
+    );
+    assert_eq!(
+        parse_file_take("./config_test.conf", ',', 3).unwrap(),
+        all[..3]
+    );
+    assert_eq!(
+        parse_file_take("./config_test.conf", ',', 100).unwrap(),
+        all
+    );
+
+    // An error after the first n options isn't reached
+    let dir = TestDir::new("parse_file_take");
+    let path = dir.join("take.conf");
+    std::fs::write(&path, b"version = 2\nname = caf\xe9\n").unwrap();
+    let path = path.to_str().unwrap();
+    assert_eq!(parse_file_take(path, ',', 1).unwrap()[0].option, "version");
+    assert!(parse_file_take(path, ',', 2).is_err());
+}
+
+#[test]
+fn test_parse_file_autodelim() {
+    assert_eq!(detect_delimiter("a = 1; 2; 3\nb = x, y\n# a, b, c, d"), ';');
+    assert_eq!(detect_delimiter("a = 1, 2; 3\nb = x; y, z"), ',');
+    assert_eq!(detect_delimiter("[a;b;c]\nflag;x\nport = 80"), ',');
+
+    let dir = TestDir::new("parse_file_autodelim");
+    let path = dir.join("autodelim.conf");
+    std::fs::write(&path, "option = Blue; light; shiny\nsizes = 1, 2; 3; 4").unwrap();
+    let (delim, config_vec) = parse_file_autodelim(path.to_str().unwrap()).unwrap();
+    assert_eq!(delim, ';');
+    assert_eq!(config_vec[0].value.attributes, vec!["light", "shiny"]);
+    assert_eq!(config_vec[1].value.primary, "1, 2");
+
+    assert!(parse_file_autodelim("./not_found.conf").is_err());
+}
+
+#[test]
+fn test_validate_file() {
+    let dir = TestDir::new("validate_file");
+    let path = dir.join("validate.conf");
+    std::fs::write(
+        &path,
+        "a = 1\nmax users = 2\nb = 3\nbad name\nscript = <<END\n",
+    )
+    .unwrap();
+    let path = path.to_str().unwrap();
+    let opts = ParseOptions {
+        block_values: true,
+        ..ParseOptions::default()
+    };
+    assert_eq!(validate_file(path, &opts).unwrap_err().len(), 1);
+    let opts = ParseOptions {
+        reject_invalid_options: true,
+        ..opts
+    };
+    let errors = validate_file(path, &opts).unwrap_err();
+    let lines: Vec<Option<usize>> = errors.iter().map(ParseError::line).collect();
+    assert_eq!(lines, vec![Some(2), Some(4), Some(5)]);
+    assert!(errors[0].to_string().starts_with(path));
+
+    let errors = validate_file("./not_found.conf", &opts).unwrap_err();
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn test_source_name() {
+    assert_eq!(source_name("-"), Path::new("<stdin>"));
+    assert_eq!(source_name(Path::new("a/b.conf")), Path::new("a/b.conf"));
+    // Opening the standard input doesn't read it
+    assert!(open_file(Path::new("-")).is_ok());
+}
+
+#[test]
+fn test_read_with_includes() {
+    // As for the standard input, relative includes start in the current
+    // directory
+    let opts = ParseOptions {
+        includes: true,
+        block_values: true,
+        ..ParseOptions::default()
+    };
+    let data = "a = 1\ninclude config_test.conf\nscript = <<END";
+    let err = read_with_includes(
+        data.as_bytes(),
+        Path::new(STDIN_NAME),
+        &opts,
+        &mut Vec::new(),
+        &|_| None,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "<stdin>: line 3: unterminated block value for 'script'"
+    );
+    let config_vec = read_with_includes(
+        "a = 1\ninclude config_test.conf".as_bytes(),
+        Path::new(STDIN_NAME),
+        &opts,
+        &mut Vec::new(),
+        &|_| None,
+    )
+    .unwrap();
+    assert_eq!(config_vec.len(), 5);
+    assert_eq!(config_vec[1].option, "option");
+}
+
+#[test]
+fn test_includes() {
+    let dir = TestDir::new("includes");
+    std::fs::create_dir_all(dir.join("conf.d")).unwrap();
+    std::fs::write(
+        dir.join("main.conf"),
+        "a = 1\ninclude conf.d/extra.conf\ninclude_if_exists missing.conf\n\
+         include_env CONFIGSTER_TEST_UNSET\ninclude_env CONFIGSTER_TEST_EXTRA\nd = 4",
+    )
+    .unwrap();
+    std::fs::write(dir.join("conf.d/extra.conf"), "[extra]\nb = 2").unwrap();
+    std::fs::write(dir.join("env.conf"), "c = 3").unwrap();
+    let env_conf = dir.join("env.conf");
+    let env = |name: &str| match name {
+        "CONFIGSTER_TEST_EXTRA" => Some(env_conf.clone().into_os_string()),
+        _ => None,
+    };
+
+    let opts = ParseOptions {
+        includes: true,
+        ..ParseOptions::default()
+    };
+    let config_vec =
+        read_file_options(&dir.join("main.conf"), &opts, &mut Vec::new(), &env).unwrap();
+    let names: Vec<&str> = config_vec.iter().map(|o| o.option.as_str()).collect();
+    assert_eq!(names, vec!["a", "b", "c", "d"]);
+    assert_eq!(config_vec[1].section.as_deref(), Some("extra"));
+    assert_eq!(config_vec[3].section, None);
+
+    // A missing file named by 'include' is an error
+    std::fs::write(dir.join("bad.conf"), "include missing.conf").unwrap();
+    let err = parse_file_with(dir.join("bad.conf").to_str().unwrap(), &opts).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(err.to_string().contains("missing.conf"));
+
+    // ... even inside a file included with 'include_if_exists'
+    std::fs::write(dir.join("optional.conf"), "include_if_exists bad.conf").unwrap();
+    let err = parse_file_with(dir.join("optional.conf").to_str().unwrap(), &opts).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(err.to_string().contains("missing.conf"));
+
+    // A cycle
+    std::fs::write(dir.join("loop1.conf"), "include loop2.conf").unwrap();
+    std::fs::write(
+        dir.join("loop2.conf"),
+        "x = 1\ninclude_if_exists loop1.conf",
+    )
+    .unwrap();
+    let err = parse_file_with(dir.join("loop1.conf").to_str().unwrap(), &opts).unwrap_err();
+    assert!(matches!(
+        err.get_ref().and_then(|e| e.downcast_ref::<ParseError>()),
+        Some(ParseError::IncludeCycle { .. })
+    ));
+
+    // A chain deeper than the limit
+    for i in 0..4 {
+        let data = format!("level{} = {}\ninclude chain{}.conf", i, i, i + 1);
+        std::fs::write(dir.join(format!("chain{}.conf", i)), data).unwrap();
+    }
+    std::fs::write(dir.join("chain4.conf"), "level4 = 4").unwrap();
+    let chain = dir.join("chain0.conf");
+    let chain = chain.to_str().unwrap();
+    assert_eq!(parse_file_with(chain, &opts).unwrap().len(), 5);
+    let opts = ParseOptions {
+        includes: true,
+        max_include_depth: 3,
+        ..ParseOptions::default()
+    };
+    let err = parse_file_with(chain, &opts).unwrap_err();
+    assert!(err
+        .to_string()
+        .ends_with("chain4.conf: includes are nested more than 3 deep"));
+}
+
+#[test]
+fn test_resolve_paths() {
+    let dir = TestDir::new("resolve_paths");
+    std::fs::create_dir_all(dir.join("conf.d/data")).unwrap();
+    std::fs::write(
+        dir.join("main.conf"),
+        "data = conf.d/./data, x\nabs = /\nother = conf.d\nflag =\ninclude conf.d/extra.conf",
+    )
+    .unwrap();
+    std::fs::write(dir.join("conf.d/extra.conf"), "cache = ../cache/app/\n").unwrap();
+    let main = dir.join("main.conf");
+    let main = main.to_str().unwrap();
+    let dir = dir.canonicalize().unwrap();
+
+    let mut opts = ParseOptions {
+        includes: true,
+        path_options: &["data", "abs", "flag", "cache"],
+        ..ParseOptions::default()
+    };
+    let config_vec = parse_file_with(main, &opts).unwrap();
+    assert_eq!(config_vec[0].value.primary, "conf.d/./data");
+
+    opts.resolve_paths = true;
+    let config_vec = parse_file_with(main, &opts).unwrap();
+    let values: Vec<&str> = config_vec
+        .iter()
+        .map(|o| o.value.primary.as_str())
+        .collect();
+    let (data, cache) = (dir.join("conf.d/data"), dir.join("cache/app"));
+    assert_eq!(
+        values,
+        vec![
+            data.to_str().unwrap(),
+            "/",
+            "conf.d",
+            "",
+            cache.to_str().unwrap()
+        ]
+    );
+    assert_eq!(config_vec[0].value.attributes, vec!["x"]);
+
+    opts.require_paths = true;
+    let err = parse_file_with(main, &opts).unwrap_err();
+    let err = err
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<ParseError>())
+        .unwrap();
+    assert!(matches!(
+        err,
+        ParseError::InFile { error, .. } if matches!(
+            **error,
+            ParseError::MissingPath { ref option, line: 1, .. } if option == "cache"
+        )
+    ));
+}
+
+#[cfg(feature = "glob")]
+#[test]
+fn test_glob_includes() {
+    let dir = TestDir::new("glob_includes");
+    std::fs::create_dir_all(dir.join("conf.d")).unwrap();
+    std::fs::write(
+        dir.join("main.conf"),
+        "a = 1\ninclude conf.d/*.conf\ninclude none.d/*.conf\nz = 26",
+    )
+    .unwrap();
+    std::fs::write(dir.join("conf.d/20-net.conf"), "c = 3").unwrap();
+    std::fs::write(dir.join("conf.d/10-base.conf"), "b = 2").unwrap();
+    std::fs::write(dir.join("conf.d/README"), "not = included").unwrap();
+
+    let opts = ParseOptions {
+        includes: true,
+        ..ParseOptions::default()
+    };
+    let config_vec = parse_file_with(dir.join("main.conf").to_str().unwrap(), &opts).unwrap();
+    let names: Vec<&str> = config_vec.iter().map(|o| o.option.as_str()).collect();
+    assert_eq!(names, vec!["a", "b", "c", "z"]);
+
+    // A matched file including the pattern again is a cycle
+    std::fs::write(dir.join("conf.d/30-loop.conf"), "include *.conf").unwrap();
+    let err = parse_file_with(dir.join("main.conf").to_str().unwrap(), &opts).unwrap_err();
+    assert!(err.to_string().ends_with("file includes itself"));
+    std::fs::remove_file(dir.join("conf.d/30-loop.conf")).unwrap();
+
+    std::fs::write(dir.join("bad.conf"), "include conf.d/[.conf").unwrap();
+    let err = parse_file_with(dir.join("bad.conf").to_str().unwrap(), &opts).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("invalid include pattern"));
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::interned::Interner;
+use crate::{Config, OptionProperties};
+
+/// A read-only configuration indexed by option name, for programs that parse
+/// a configuration once and look options up many times. Lookups go through a
+/// hash map from each distinct name to the positions of its options, instead
+/// of scanning the options as [get_in_section](fn.get_in_section.html) does.
+/// The names are interned: each distinct name is stored once, in an
+/// `Arc<str>` shared by the map key and [names](#method.names). It
+/// dereferences to a slice of the options, in their original order.
+///
+/// # Examples
+///
+/// ```
+/// use configster::FrozenConfig;
+///
+/// let config_vec = configster::parse_str("server = a\nport = 80\nserver = b", ',').unwrap();
+/// let cfg = FrozenConfig::from(config_vec);
+/// assert_eq!(cfg.get("port").unwrap().value.primary, "80");
+/// assert_eq!(cfg.get_all("server").count(), 2);
+/// assert!(cfg.get("user").is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct FrozenConfig {
+    options: Vec<OptionProperties>,
+    /// The interned name of each option
+    names: Vec<Arc<str>>,
+    /// The positions of the options with each name, in order
+    index: HashMap<Arc<str>, Vec<usize>>,
+}
+
+impl FrozenConfig {
+    /// Returns the first option named `option`.
+    pub fn get(&self, option: &str) -> Option<&OptionProperties> {
+        self.get_all(option).next()
+    }
+
+    /// Returns the options named `option`, in the order they appear in the
+    /// file.
+    pub fn get_all<'a>(&'a self, option: &str) -> impl Iterator<Item = &'a OptionProperties> {
+        self.index
+            .get(option)
+            .map_or(&[][..], Vec::as_slice)
+            .iter()
+            .map(move |&i| &self.options[i])
+    }
+
+    /// Returns the first option named `option` within `section` (`None` for
+    /// the options that come before any section header).
+    pub fn get_in_section(&self, section: Option<&str>, option: &str) -> Option<&OptionProperties> {
+        self.get_all(option)
+            .find(|o| o.section.as_deref() == section)
+    }
+
+    /// Returns true if there is an option named `option`.
+    pub fn contains(&self, option: &str) -> bool {
+        self.index.contains_key(option)
+    }
+
+    /// Returns the name of each option, in order. Equal names share one
+    /// allocation.
+    pub fn names(&self) -> &[Arc<str>] {
+        &self.names
+    }
+
+    /// Returns the options as a slice.
+    pub fn options(&self) -> &[OptionProperties] {
+        &self.options
+    }
+
+    /// Consumes the `FrozenConfig`, returning the options vector.
+    pub fn into_vec(self) -> Vec<OptionProperties> {
+        self.options
+    }
+}
+
+impl From<Vec<OptionProperties>> for FrozenConfig {
+    fn from(options: Vec<OptionProperties>) -> Self {
+        let mut strings = Interner::default();
+        let names: Vec<Arc<str>> = options.iter().map(|o| strings.intern(&o.option)).collect();
+        let mut index: HashMap<Arc<str>, Vec<usize>> = HashMap::new();
+        for (i, name) in names.iter().enumerate() {
+            index.entry(name.clone()).or_default().push(i);
+        }
+        Self {
+            options,
+            names,
+            index,
+        }
+    }
+}
+
+impl From<Config> for FrozenConfig {
+    fn from(cfg: Config) -> Self {
+        Self::from(cfg.into_vec())
+    }
+}
+
+impl Deref for FrozenConfig {
+    type Target = [OptionProperties];
+
+    fn deref(&self) -> &Self::Target {
+        &self.options
+    }
+}
+
+#[test]
+fn test_frozen_config() {
+    let cfg: Config = "level = 1\n[logging]\nlevel = 2\nDelayOff\n[network]\nlevel = 3"
+        .parse()
+        .unwrap();
+    let frozen = FrozenConfig::from(cfg.clone());
+    assert_eq!(frozen.len(), 4);
+    assert_eq!(frozen.options(), cfg.options());
+    assert_eq!(frozen.get("level").unwrap().value.primary, "1");
+    let levels: Vec<&str> = frozen
+        .get_all("level")
+        .map(|o| o.value.primary.as_str())
+        .collect();
+    assert_eq!(levels, vec!["1", "2", "3"]);
+    assert_eq!(
+        frozen
+            .get_in_section(Some("network"), "level")
+            .unwrap()
+            .value
+            .primary,
+        "3"
+    );
+    assert!(frozen.get_in_section(None, "DelayOff").is_none());
+    assert!(frozen.contains("DelayOff"));
+    assert_eq!(frozen.get_all("missing").count(), 0);
+
+    // The equal names are one allocation, shared with the index
+    let names = frozen.names();
+    assert_eq!(&*names[2], "DelayOff");
+    assert!(Arc::ptr_eq(&names[0], &names[1]));
+    assert!(Arc::ptr_eq(&names[0], &names[3]));
+    let (key, _) = frozen.index.get_key_value("level").unwrap();
+    assert!(Arc::ptr_eq(key, &names[0]));
+    assert_eq!(frozen.into_vec(), cfg.into_vec());
+}
@@ -0,0 +1,106 @@
+use alloc::collections::BTreeSet;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::parse::{only_options, LineParser};
+use crate::{OptionProperties, ParseError, ParseOptions};
+
+/// An option whose strings are shared with the equal strings of the other
+/// options, as returned by [parse_str_interned](fn.parse_str_interned.html).
+/// Otherwise it holds the same as [OptionProperties](struct.OptionProperties.html).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InternedOption {
+    pub option: Arc<str>,
+    pub section: Option<Arc<str>>,
+    pub primary: Arc<str>,
+    pub attributes: Vec<Arc<str>>,
+}
+
+/// Like [parse_str_with](fn.parse_str_with.html), but each distinct string
+/// (option name, section, primary value or attribute) is stored once, in an
+/// `Arc<str>` shared by every option using it. This saves memory in large
+/// configurations where the same values (e.g. "ro" and "rw") are repeated
+/// many times. The options are interned as they are parsed, so the strings
+/// of only one option are allocated separately at a time.
+///
+/// # Examples
+///
+/// ```
+/// use configster::ParseOptions;
+/// use std::sync::Arc;
+///
+/// let data = "/home = ext4, rw\n/srv = ext4, ro\n/tmp = tmpfs, rw";
+/// let config_vec = configster::parse_str_interned(data, &ParseOptions::default()).unwrap();
+/// assert_eq!(&*config_vec[2].attributes[0], "rw");
+/// assert!(Arc::ptr_eq(&config_vec[0].attributes[0], &config_vec[2].attributes[0]));
+/// assert!(Arc::ptr_eq(&config_vec[0].primary, &config_vec[1].primary));
+/// ```
+pub fn parse_str_interned(s: &str, opts: &ParseOptions) -> Result<Vec<InternedOption>, ParseError> {
+    let mut strings = Interner::default();
+    only_options(LineParser::new(s.lines().map(Ok), opts))
+        .map(|opt_props| opt_props.map(|o| strings.option(o)))
+        .collect()
+}
+
+/// Keeps one `Arc<str>` for each distinct string, to share it.
+#[derive(Default)]
+pub(crate) struct Interner {
+    strings: BTreeSet<Arc<str>>,
+}
+
+impl Interner {
+    pub(crate) fn intern(&mut self, s: &str) -> Arc<str> {
+        if let Some(shared) = self.strings.get(s) {
+            return shared.clone();
+        }
+        let shared: Arc<str> = s.into();
+        self.strings.insert(shared.clone());
+        shared
+    }
+
+    fn option(&mut self, opt_props: OptionProperties) -> InternedOption {
+        InternedOption {
+            option: self.intern(&opt_props.option),
+            section: opt_props.section.map(|s| self.intern(&s)),
+            primary: self.intern(&opt_props.value.primary),
+            attributes: opt_props
+                .value
+                .attributes
+                .iter()
+                .map(|a| self.intern(a))
+                .collect(),
+        }
+    }
+}
+
+#[test]
+fn test_parse_str_interned() {
+    let data = "a = x, y, x\n[x]\nb = y\nb = z, a";
+    let config_vec = parse_str_interned(data, &ParseOptions::default()).unwrap();
+    assert_eq!(config_vec.len(), 3);
+    let plain = crate::parse_str(data, ',').unwrap();
+    for (interned, o) in config_vec.iter().zip(&plain) {
+        assert_eq!(&*interned.option, o.option);
+        assert_eq!(interned.section.as_deref(), o.section.as_deref());
+        assert_eq!(&*interned.primary, o.value.primary);
+        let attributes: Vec<&str> = interned.attributes.iter().map(|a| &**a).collect();
+        assert_eq!(attributes, o.value.attributes);
+    }
+
+    // Equal strings are shared, whatever their role
+    let first = &config_vec[0];
+    assert!(Arc::ptr_eq(&first.primary, &first.attributes[1]));
+    assert!(Arc::ptr_eq(
+        &first.primary,
+        config_vec[1].section.as_ref().unwrap()
+    ));
+    assert!(Arc::ptr_eq(&first.attributes[0], &config_vec[1].primary));
+    assert!(Arc::ptr_eq(&first.option, &config_vec[2].attributes[0]));
+    assert!(Arc::ptr_eq(&config_vec[1].option, &config_vec[2].option));
+
+    let opts = ParseOptions {
+        block_values: true,
+        ..ParseOptions::default()
+    };
+    assert!(parse_str_interned("a = <<END", &opts).is_err());
+}
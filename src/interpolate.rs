@@ -0,0 +1,304 @@
+use std::env;
+
+use crate::{OptionProperties, ParseError};
+
+/// Replaces `${name}` references in the values of the options with the primary
+/// value of the option called `name`. A reference refers to an option defined
+/// before it: the nearest one in the same section, or else the nearest one in
+/// any section. A later option of that name isn't used, so an option may refer
+/// to its own earlier definition (`path = ${path}:/opt/bin`) and references
+/// can't form cycles. When `use_env` is true, a name that isn't such an option
+/// is looked up in the environment instead (see
+/// [interpolate_with](fn.interpolate_with.html) to look it up elsewhere).
+///
+/// As in a shell, `${name:-default}` is replaced with `default` if `name` is
+/// undefined or empty, and `${name:+alt}` with `alt` if `name` is defined and
+/// not empty (and with nothing otherwise). The references in `default` and
+/// `alt` are expanded too, e.g. `${user:+--user=${user}}`.
+///
+/// A reference to a name that can't be found (other than with `:-` or `:+`)
+/// is an error: [ParseError::SelfReference](enum.ParseError.html#variant.SelfReference)
+/// for the name of the option itself, and `UndefinedReference` otherwise.
+///
+/// # Examples
+///
+/// ```
+/// let mut config_vec = configster::parse_str("base = /opt/app\nlogdir = ${base}/logs", ',').unwrap();
+/// configster::interpolate(&mut config_vec, false).unwrap();
+/// assert_eq!(config_vec[1].value.primary, "/opt/app/logs");
+///
+/// let mut config_vec = configster::parse_str("logdir = ${LOG_DIR:-/var/log/app}", ',').unwrap();
+/// configster::interpolate(&mut config_vec, false).unwrap();
+/// assert_eq!(config_vec[0].value.primary, "/var/log/app");
+/// ```
+pub fn interpolate(vec: &mut [OptionProperties], use_env: bool) -> Result<(), ParseError> {
+    interpolate_with(vec, |name| match use_env {
+        true => env::var(name).ok(),
+        false => None,
+    })
+}
+
+/// Like [interpolate](fn.interpolate.html), but a name that isn't an earlier
+/// option is looked up with `env`, which returns `None` for an undefined name.
+///
+/// # Examples
+///
+/// ```
+/// let mut config_vec = configster::parse_str("logdir = ${HOME}/logs", ',').unwrap();
+/// configster::interpolate_with(&mut config_vec, |name| match name {
+///     "HOME" => Some("/home/foo".to_string()),
+///     _ => None,
+/// })
+/// .unwrap();
+/// assert_eq!(config_vec[0].value.primary, "/home/foo/logs");
+/// ```
+pub fn interpolate_with<F>(vec: &mut [OptionProperties], env: F) -> Result<(), ParseError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    // The options before the one being expanded are already expanded
+    for i in 0..vec.len() {
+        let (earlier, rest) = vec.split_at_mut(i);
+        let OptionProperties {
+            option,
+            value,
+            section,
+            ..
+        } = &mut rest[0];
+        let section = section.as_deref();
+        let mut lookup = |name: &str| Ok(lookup(name, section, earlier).or_else(|| env(name)));
+        let mut undefined = |name: &str| undefined(name, option, i);
+        value.primary = expand(&value.primary, &mut lookup, &mut undefined)?;
+        for attr in &mut value.attributes {
+            *attr = expand(attr, &mut lookup, &mut undefined)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the value of the option called `name` for an option in `section`,
+/// given the options before it, or `None` if there is none.
+fn lookup(name: &str, section: Option<&str>, earlier: &[OptionProperties]) -> Option<String> {
+    let mut named = earlier.iter().rev().filter(|o| o.option == name);
+    named
+        .clone()
+        .find(|o| o.section.as_deref() == section)
+        .or_else(|| named.next())
+        .map(|opt| opt.value.primary.clone())
+}
+
+/// Returns the error for an undefined `${name}` in the value of `option`, the
+/// option at index `i`.
+fn undefined(name: &str, option: &str, i: usize) -> Result<String, ParseError> {
+    if name == option {
+        return Err(ParseError::SelfReference {
+            option: option.to_string(),
+            line: i + 1,
+        });
+    }
+    Err(ParseError::UndefinedReference {
+        option: option.to_string(),
+        name: name.to_string(),
+    })
+}
+
+/// Expands each `${name}` (and `${name:-default}` and `${name:+alt}`) in `s`
+/// using `lookup`, which returns `None` for an undefined name; `undefined`
+/// gives the expansion of an undefined `${name}`. The word after `:-` or `:+`
+/// may contain references of its own, so a reference ends at the matching
+/// `}`. A `$` that isn't followed by a `{...}` group is kept as it is.
+pub(crate) fn expand<F, U, E>(s: &str, lookup: &mut F, undefined: &mut U) -> Result<String, E>
+where
+    F: FnMut(&str) -> Result<Option<String>, E>,
+    U: FnMut(&str) -> Result<String, E>,
+{
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        let end = match closing_brace(&rest[start + 2..]) {
+            Some(end) => start + 2 + end,
+            None => break,
+        };
+        out.push_str(&rest[..start]);
+        let reference = &rest[start + 2..end];
+        let operator = reference
+            .find(":-")
+            .into_iter()
+            .chain(reference.find(":+"))
+            .min();
+        match operator {
+            Some(i) => {
+                let (name, word) = (&reference[..i], &reference[i + 2..]);
+                let value = lookup(name)?.filter(|v| !v.is_empty());
+                match (&reference[i + 1..i + 2], value) {
+                    ("-", Some(value)) => out.push_str(&value),
+                    ("-", None) | ("+", Some(_)) => out.push_str(&expand(word, lookup, undefined)?),
+                    _ => {}
+                }
+            }
+            None => match lookup(reference)? {
+                Some(value) => out.push_str(&value),
+                None => out.push_str(&undefined(reference)?),
+            },
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Returns the position of the `}` ending a reference in `s` (which starts
+/// after its `${`), skipping the references nested in it.
+fn closing_brace(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut bytes = s.bytes().enumerate().peekable();
+    while let Some((i, b)) = bytes.next() {
+        match b {
+            b'$' if bytes.peek().map(|&(_, b)| b) == Some(b'{') => {
+                bytes.next();
+                depth += 1;
+            }
+            b'}' if depth == 0 => return Some(i),
+            b'}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+#[test]
+fn test_interpolate() {
+    let mut vec = crate::parse_str(
+        "root = /opt\nuser = me\nbase = ${root}/app\nlogdir = ${base}/logs, ${user}\nprice = $5",
+        ',',
+    )
+    .unwrap();
+    interpolate(&mut vec, false).unwrap();
+    assert_eq!(vec[3].value.primary, "/opt/app/logs");
+    assert_eq!(vec[3].value.attributes, vec!["me".to_string()]);
+    assert_eq!(vec[2].value.primary, "/opt/app");
+    assert_eq!(vec[4].value.primary, "$5");
+
+    // Unterminated references are kept literally
+    let mut vec = crate::parse_str("a = ${b\nc = ${a:-${b}", ',').unwrap();
+    interpolate(&mut vec, false).unwrap();
+    assert_eq!(vec[0].value.primary, "${b");
+    assert_eq!(vec[1].value.primary, "${a:-${b}");
+}
+
+#[test]
+fn test_interpolate_env() {
+    let env = |name: &str| match name {
+        "HOME" => Some("/home/foo".to_string()),
+        _ => None,
+    };
+    let mut vec = crate::parse_str(
+        "dir = ${HOME}/Documents
+x = ${UNSET}",
+        ',',
+    )
+    .unwrap();
+    assert!(matches!(
+        interpolate_with(&mut vec[..1], |_| None),
+        Err(ParseError::UndefinedReference { .. })
+    ));
+    assert!(matches!(
+        interpolate_with(&mut vec, env),
+        Err(ParseError::UndefinedReference { ref name, .. }) if name == "UNSET"
+    ));
+    assert_eq!(vec[0].value.primary, "/home/foo/Documents");
+
+    // An earlier option comes before the environment
+    let mut vec = crate::parse_str(
+        "HOME = /root
+dir = ${HOME}",
+        ',',
+    )
+    .unwrap();
+    interpolate_with(&mut vec, env).unwrap();
+    assert_eq!(vec[1].value.primary, "/root");
+}
+
+#[test]
+fn test_interpolate_defaults() {
+    let env = |name: &str| match name {
+        "CONFIGSTER_TEST_LOG_DIR" => Some("/tmp/logs".to_string()),
+        _ => None,
+    };
+    let data = "\
+empty =
+name = app
+a = ${CONFIGSTER_TEST_UNSET:-/var/log/app}
+b = ${CONFIGSTER_TEST_LOG_DIR:-/var/log/app}
+c = ${empty:-fallback}, ${name:-x:-y}
+d = [${CONFIGSTER_TEST_LOG_DIR:+--logs}] [${CONFIGSTER_TEST_UNSET:+--logs}] [${empty:+x}]
+e = ${name:+${name}-${name:-x}}, ${CONFIGSTER_TEST_UNSET:-${name:+[${name}]}}x";
+    let mut vec = crate::parse_str(data, ',').unwrap();
+    interpolate_with(&mut vec, env).unwrap();
+    let primaries: Vec<&str> = vec.iter().map(|o| o.value.primary.as_str()).collect();
+    assert_eq!(
+        primaries,
+        vec![
+            "",
+            "app",
+            "/var/log/app",
+            "/tmp/logs",
+            "fallback",
+            "[--logs] [] []",
+            "app-app"
+        ]
+    );
+    assert_eq!(vec[4].value.attributes, vec!["app"]);
+    assert_eq!(vec[6].value.attributes, vec!["[app]x"]);
+
+    // Without the environment, the variables are undefined
+    let mut vec = crate::parse_str(data, ',').unwrap();
+    interpolate(&mut vec, false).unwrap();
+    assert_eq!(vec[3].value.primary, "/var/log/app");
+
+    // A later option is undefined for the default
+    let mut vec = crate::parse_str("a = ${b:-x}\nb = ${a}", ',').unwrap();
+    interpolate(&mut vec, false).unwrap();
+    assert_eq!(vec[0].value.primary, "x");
+    assert_eq!(vec[1].value.primary, "x");
+}
+
+#[test]
+fn test_interpolate_earlier_options() {
+    // Only the options defined before a reference are used
+    let mut vec = crate::parse_str("a = ${b}\nb = 1", ',').unwrap();
+    assert!(matches!(
+        interpolate(&mut vec, false),
+        Err(ParseError::UndefinedReference { option, name }) if option == "a" && name == "b"
+    ));
+    let mut vec = crate::parse_str("b = 1\na = ${a}", ',').unwrap();
+    let err = interpolate(&mut vec, false).unwrap_err();
+    assert!(matches!(
+        err,
+        ParseError::SelfReference { ref option, line: 2 } if option == "a"
+    ));
+    assert_eq!(
+        err.to_string(),
+        "option 2: 'a' refers to itself, with no earlier definition"
+    );
+    let mut vec = crate::parse_str("a = ${a:-x}, ${b}", ',').unwrap();
+    assert!(matches!(
+        interpolate(&mut vec, false),
+        Err(ParseError::UndefinedReference { .. })
+    ));
+
+    // The nearest earlier definition is used, so an option can extend itself
+    let data = "path = /bin\npath = ${path}:/usr/bin\nv = ${path}\npath = /sbin";
+    let mut vec = crate::parse_str(data, ',').unwrap();
+    interpolate(&mut vec, false).unwrap();
+    assert_eq!(vec[1].value.primary, "/bin:/usr/bin");
+    assert_eq!(vec[2].value.primary, "/bin:/usr/bin");
+
+    // The same section comes first, then the nearest in any section
+    let data =
+        "port = 1\n[db]\nport = 2\n[web]\nport = 3\n[db]\nurl = :${port}\n[app]\nurl = :${port}";
+    let mut vec = crate::parse_str(data, ',').unwrap();
+    interpolate(&mut vec, false).unwrap();
+    assert_eq!(vec[3].value.primary, ":2");
+    assert_eq!(vec[4].value.primary, ":3");
+}
@@ -0,0 +1,64 @@
+/// Builds a `Vec<OptionProperties>`, e.g. to compare against parsed output.
+/// Each option is written as `"option" => "primary"`, optionally followed by
+/// a list of attributes; options are separated by semicolons. The options
+/// have no section.
+///
+/// # Examples
+///
+/// ```
+/// use configster::config;
+///
+/// let data = "option = Blue, light, shiny\nmax_users = 30\nDelayOff";
+/// assert_eq!(
+///     configster::parse_str(data, ',').unwrap(),
+///     config![
+///         "option" => "Blue", ["light", "shiny"];
+///         "max_users" => "30";
+///         "DelayOff" => "";
+///     ]
+/// );
+/// ```
+#[macro_export]
+macro_rules! config {
+    ($($option:expr => $primary:expr $(, [$($attr:expr),* $(,)?])?);* $(;)?) => {{
+        let v: $crate::__private::Vec<$crate::OptionProperties> = $crate::__private::vec![$(
+            $crate::OptionProperties::new(
+                $crate::__private::String::from($option),
+                $crate::__private::String::from($primary),
+                $crate::__private::vec![$($($crate::__private::String::from($attr)),*)?],
+            )
+        ),*];
+        v
+    }};
+}
+
+#[test]
+fn test_config_macro() {
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    let v = config![
+        "option" => "Blue", ["light", "shiny",];
+        "DelayOff" => ""
+    ];
+    assert_eq!(
+        v,
+        vec![
+            crate::OptionProperties::new(
+                "option".to_string(),
+                "Blue".to_string(),
+                vec!["light".to_string(), "shiny".to_string()],
+            ),
+            crate::OptionProperties::new("DelayOff".to_string(), "".to_string(), vec![]),
+        ]
+    );
+    assert!(config![].is_empty());
+
+    // A local `None` doesn't change the expansion
+    #[allow(non_upper_case_globals, dead_code)]
+    const None: () = ();
+    assert_eq!(
+        config!["a" => "1"][0].section,
+        Option::<alloc::string::String>::None
+    );
+}
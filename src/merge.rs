@@ -0,0 +1,209 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::OptionProperties;
+
+/// Merges two option vectors, with the options in `over` taking precedence.
+///
+/// Each option in `over` replaces an option in `base` with the same name and
+/// section, keeping the position of the replaced option; options without a
+/// match in `base` are appended, except that those without a section go
+/// before the first section of `base`, so that they stay outside of any
+/// section when the options are written. If an option occurs more than once,
+/// the occurrences are paired up in order.
+///
+/// # Examples
+///
+/// ```
+/// let base = configster::parse_str("max_users = 30\ncolor = blue", ',').unwrap();
+/// let over = configster::parse_str("color = red\nDelayOff", ',').unwrap();
+/// let merged = configster::merge(base, over);
+/// let names: Vec<(&str, &str)> = merged
+///     .iter()
+///     .map(|o| (o.option.as_str(), o.value.primary.as_str()))
+///     .collect();
+/// assert_eq!(names, vec![("max_users", "30"), ("color", "red"), ("DelayOff", "")]);
+/// ```
+pub fn merge(
+    mut base: Vec<OptionProperties>,
+    over: Vec<OptionProperties>,
+) -> Vec<OptionProperties> {
+    merge_into(&mut base, over);
+    base
+}
+
+/// Merges `over` into `base`, as [merge](fn.merge.html) does.
+pub(crate) fn merge_into(base: &mut Vec<OptionProperties>, over: Vec<OptionProperties>) {
+    // The position in `base` each option of `over` replaces, if any
+    let targets: Vec<Option<usize>> = {
+        // The positions of the occurrences of each option, last one first
+        let mut index: BTreeMap<(Option<&str>, &str), Vec<usize>> = BTreeMap::new();
+        for (i, o) in base.iter().enumerate().rev() {
+            index
+                .entry((o.section.as_deref(), o.option.as_str()))
+                .or_default()
+                .push(i);
+        }
+        over.iter()
+            .map(|o| {
+                index
+                    .get_mut(&(o.section.as_deref(), o.option.as_str()))
+                    .and_then(Vec::pop)
+            })
+            .collect()
+    };
+
+    let mut top_level = Vec::new();
+    let mut in_sections = Vec::new();
+    for (opt, target) in over.into_iter().zip(targets) {
+        match target {
+            Some(i) => base[i] = opt,
+            None if opt.section.is_none() => top_level.push(opt),
+            None => in_sections.push(opt),
+        }
+    }
+    let first_section = base
+        .iter()
+        .position(|o| o.section.is_some())
+        .unwrap_or(base.len());
+    base.splice(first_section..first_section, top_level);
+    base.extend(in_sections);
+}
+
+/// Inserts an option where it stays in its section when the options are
+/// written: an option without a section goes before the first one in a
+/// section, and one in a section after the last option of that section (or at
+/// the end). Returns the position of the option.
+pub(crate) fn insert_option(vec: &mut Vec<OptionProperties>, opt_props: OptionProperties) -> usize {
+    let i = match &opt_props.section {
+        None => vec.iter().position(|o| o.section.is_some()),
+        Some(section) => vec
+            .iter()
+            .rposition(|o| o.section.as_ref() == Some(section))
+            .map(|i| i + 1),
+    };
+    let i = i.unwrap_or(vec.len());
+    vec.insert(i, opt_props);
+    i
+}
+
+/// Overrides options with environment variables: for each option, if the
+/// variable named `prefix` followed by the option name in upper case is set
+/// (`APP_MAX_USERS` for "max_users" with the prefix "APP_"), its value
+/// replaces the primary value. Characters of the name other than ASCII letters
+/// and digits become '_' in the variable name. The attributes are kept, and
+/// options in every section are overridden alike. Use
+/// [overlay_with](fn.overlay_with.html) to look the variables up elsewhere.
+///
+/// # Examples
+///
+/// ```no_run
+/// let mut config_vec = configster::parse_str("max_users = 30\ncolor = blue", ',').unwrap();
+/// // With APP_MAX_USERS=50 in the environment
+/// configster::overlay_env(&mut config_vec, "APP_");
+/// assert_eq!(config_vec[0].value.primary, "50");
+/// ```
+#[cfg(feature = "std")]
+pub fn overlay_env(vec: &mut [OptionProperties], prefix: &str) {
+    overlay_with(vec, prefix, |name| {
+        std::env::var_os(name).map(|v| v.to_string_lossy().into_owned())
+    });
+}
+
+/// Like [overlay_env](fn.overlay_env.html), but the variables are looked up
+/// with `lookup`, which returns `None` for a variable that isn't set.
+///
+/// # Examples
+///
+/// ```
+/// let mut config_vec = configster::parse_str("max_users = 30\ncolor = blue", ',').unwrap();
+/// configster::overlay_with(&mut config_vec, "APP_", |name| match name {
+///     "APP_MAX_USERS" => Some("50".to_string()),
+///     _ => None,
+/// });
+/// assert_eq!(config_vec[0].value.primary, "50");
+/// assert_eq!(config_vec[1].value.primary, "blue");
+/// ```
+pub fn overlay_with<F>(vec: &mut [OptionProperties], prefix: &str, lookup: F)
+where
+    F: Fn(&str) -> Option<String>,
+{
+    for opt_props in vec {
+        let mut name = String::from(prefix);
+        name.extend(opt_props.option.chars().map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' => c.to_ascii_uppercase(),
+            _ => '_',
+        }));
+        if let Some(value) = lookup(&name) {
+            opt_props.value.primary = value;
+        }
+    }
+}
+
+#[test]
+fn test_merge() {
+    let base =
+        crate::parse_str("server = a\nserver = b\nport = 80\n[db]\nport = 5432", ',').unwrap();
+    let over =
+        crate::parse_str("server = c\nserver = d\nserver = e\n[db]\nport = 6543", ',').unwrap();
+    let merged = merge(base, over);
+    let merged: Vec<(Option<&str>, &str, &str)> = merged
+        .iter()
+        .map(|o| {
+            (
+                o.section.as_deref(),
+                o.option.as_str(),
+                o.value.primary.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        merged,
+        vec![
+            (None, "server", "c"),
+            (None, "server", "d"),
+            (None, "port", "80"),
+            (None, "server", "e"),
+            (Some("db"), "port", "6543"),
+        ]
+    );
+}
+
+#[test]
+fn test_overlay_with() {
+    let mut config_vec = crate::parse_str(
+        "port = 80, tcp\nhost = a\nlog-level = 2\n[db]\nport = 5432",
+        ',',
+    )
+    .unwrap();
+    overlay_with(&mut config_vec, "APP_", |name| match name {
+        "APP_PORT" => Some("8080".into()),
+        "APP_LOG_LEVEL" => Some("".into()),
+        _ => None,
+    });
+    let primaries: Vec<&str> = config_vec
+        .iter()
+        .map(|o| o.value.primary.as_str())
+        .collect();
+    assert_eq!(primaries, vec!["8080", "a", "", "8080"]);
+    assert_eq!(config_vec[0].value.attributes, vec!["tcp"]);
+}
+
+#[test]
+fn test_insert_option() {
+    let mut vec = crate::parse_str("a\n[s]\nb\n[t]\nc\n[s]\nd", ',').unwrap();
+    let opt = |name: &str, section: Option<&str>| {
+        let mut opt_props = OptionProperties::new(name.into(), "".into(), Vec::new());
+        opt_props.section = section.map(Into::into);
+        opt_props
+    };
+    assert_eq!(insert_option(&mut vec, opt("e", None)), 1);
+    assert_eq!(insert_option(&mut vec, opt("f", Some("s"))), 5);
+    assert_eq!(insert_option(&mut vec, opt("g", Some("u"))), 6);
+    let names: Vec<&str> = vec.iter().map(|o| o.option.as_str()).collect();
+    assert_eq!(names, vec!["a", "e", "b", "c", "d", "f", "g"]);
+
+    let mut vec = Vec::new();
+    assert_eq!(insert_option(&mut vec, opt("a", None)), 0);
+}
@@ -0,0 +1,463 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use core::cell::{RefCell, RefMut};
+use core::fmt;
+
+use crate::DEFAULT_ATTR_DELIMIT_CHAR;
+
+/// The delimiter separating the primary value and the attributes.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Delimiter {
+    /// A single character, e.g. ','.
+    Char(char),
+    /// A string of one or more characters, e.g. "::". An empty string
+    /// disables attribute parsing.
+    Str(String),
+    /// Runs of whitespace, for files with whitespace-separated columns. With
+    /// [whitespace_separator](struct.ParseOptions.html#structfield.whitespace_separator)
+    /// also set, a line such as an fstab entry becomes the option (first
+    /// column), the primary value (second column) and the attributes.
+    Whitespace,
+}
+
+impl Delimiter {
+    /// Splits `s` at the first delimiter.
+    pub(crate) fn split_once<'a>(&self, s: &'a str) -> Option<(&'a str, &'a str)> {
+        match self {
+            Delimiter::Char(c) if c.is_ascii() => {
+                let i = memchr::memchr(*c as u8, s.as_bytes())?;
+                Some((&s[..i], &s[i + 1..]))
+            }
+            Delimiter::Char(c) => s.split_once(*c),
+            Delimiter::Str(d) if d.is_empty() => None,
+            Delimiter::Str(d) => s.split_once(d.as_str()),
+            Delimiter::Whitespace => s.trim().split_once(char::is_whitespace),
+        }
+    }
+
+    /// Splits `s` at every delimiter.
+    pub(crate) fn split<'d, 's>(&'d self, s: &'s str) -> Split<'d, 's> {
+        match self {
+            Delimiter::Char(c) if c.is_ascii() => Split::Byte {
+                s,
+                positions: memchr::memchr_iter(*c as u8, s.as_bytes()),
+                start: Some(0),
+            },
+            Delimiter::Char(c) => Split::Char(s.split(*c)),
+            Delimiter::Str(d) if d.is_empty() => Split::Whole(Some(s)),
+            Delimiter::Str(d) => Split::Str(s.split(d.as_str())),
+            Delimiter::Whitespace => Split::Whitespace(s.split_whitespace()),
+        }
+    }
+}
+
+/// The iterator returned by [Delimiter::split](enum.Delimiter.html#method.split).
+pub(crate) enum Split<'d, 's> {
+    /// An ASCII delimiter, found with `memchr`; `start` is the beginning of the
+    /// next part, or `None` after the last part.
+    Byte {
+        s: &'s str,
+        positions: memchr::Memchr<'s>,
+        start: Option<usize>,
+    },
+    Char(core::str::Split<'s, char>),
+    Str(core::str::Split<'s, &'d str>),
+    Whole(Option<&'s str>),
+    Whitespace(core::str::SplitWhitespace<'s>),
+}
+
+impl<'s> Iterator for Split<'_, 's> {
+    type Item = &'s str;
+
+    fn next(&mut self) -> Option<&'s str> {
+        match self {
+            Split::Byte {
+                s,
+                positions,
+                start,
+            } => {
+                let begin = (*start)?;
+                match positions.next() {
+                    Some(i) => {
+                        *start = Some(i + 1);
+                        Some(&s[begin..i])
+                    }
+                    None => {
+                        *start = None;
+                        Some(&s[begin..])
+                    }
+                }
+            }
+            Split::Char(split) => split.next(),
+            Split::Str(split) => split.next(),
+            Split::Whole(s) => s.take(),
+            Split::Whitespace(split) => split.next(),
+        }
+    }
+}
+
+impl From<char> for Delimiter {
+    fn from(c: char) -> Self {
+        Delimiter::Char(c)
+    }
+}
+
+impl From<&str> for Delimiter {
+    fn from(s: &str) -> Self {
+        Delimiter::Str(s.to_string())
+    }
+}
+
+/// A callback stored in [ParseOptions](struct.ParseOptions.html), e.g.
+/// `Callback::new(Box::new(|name: &str, line| ...))`.
+pub struct Callback<F: ?Sized>(RefCell<Box<F>>);
+
+impl<F: ?Sized> Callback<F> {
+    pub fn new(f: Box<F>) -> Self {
+        Self(RefCell::new(f))
+    }
+
+    pub(crate) fn get(&self) -> RefMut<'_, Box<F>> {
+        self.0.borrow_mut()
+    }
+}
+
+impl<F: ?Sized> fmt::Debug for Callback<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Callback")
+    }
+}
+
+/// The callback type of [ParseOptions::on_unknown](struct.ParseOptions.html#structfield.on_unknown);
+/// called with an option name and its line number.
+pub type OnUnknown<'a> = Callback<dyn FnMut(&str, usize) + 'a>;
+
+/// The callback type of [ParseOptions::on_section](struct.ParseOptions.html#structfield.on_section);
+/// called with a section name and the line number of its header.
+pub type OnSection<'a> = Callback<dyn FnMut(&str, usize) + 'a>;
+
+/// The callback type of [ParseOptions::value_transform](struct.ParseOptions.html#structfield.value_transform);
+/// called with a value, it returns the value to store.
+pub type ValueTransform<'a> = Callback<dyn FnMut(&str) -> String + 'a>;
+
+/// The callback type of [ParseOptions::on_unknown_escape](struct.ParseOptions.html#structfield.on_unknown_escape);
+/// called with an escaped character and its line number, it returns the
+/// character to use instead, or `None` to keep the escape as it is.
+pub type OnUnknownEscape<'a> = Callback<dyn FnMut(char, usize) -> Option<char> + 'a>;
+
+/// The type of an option's value in a schema; see
+/// [ParseOptions::with_schema](struct.ParseOptions.html#method.with_schema).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// A single value; the delimiter is part of the primary value.
+    Scalar,
+    /// A primary value and a list of attributes.
+    List,
+    /// Like `List`, but the primary value and every attribute must be a
+    /// (finite) number, such as "3" or "-0.5"; anything else is an error
+    /// ([ParseError::NotANumber](enum.ParseError.html#variant.NotANumber)).
+    /// An option without a value is allowed.
+    Numbers,
+    /// A list with no primary value: the primary value is empty and every
+    /// item, including the first one, is an attribute ("servers = a:1, b:2").
+    Items,
+}
+
+/// What [parse_file_encoded](fn.parse_file_encoded.html) does with bytes
+/// that aren't valid in the encoding of the file.
+#[cfg(feature = "encoding")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unmappable {
+    /// Fail with [ParseError::Decode](enum.ParseError.html#variant.Decode).
+    Error,
+    /// Replace them with U+FFFD (the replacement character).
+    Replace,
+}
+
+/// A letter case, for [ParseOptions::normalize_attributes](struct.ParseOptions.html#structfield.normalize_attributes).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Case {
+    Lower,
+    Upper,
+}
+
+/// Settings that control how a configuration is parsed, for use with
+/// [parse_file_with](fn.parse_file_with.html) and the other `_with` functions.
+///
+/// Fields are added to this struct as the parser gains features, so it is
+/// `#[non_exhaustive]`: outside this crate it can't be built with a struct
+/// expression, not even one ending in `..ParseOptions::default()`. Start
+/// from [ParseOptions::default](#impl-Default),
+/// [with_delimiter](#method.with_delimiter) or
+/// [with_schema](#method.with_schema) and set the fields needed.
+///
+/// # Examples
+///
+/// Only allowing `[A-Za-z0-9_.]` in option names:
+///
+/// ```
+/// use configster::ParseOptions;
+///
+/// let mut opts = ParseOptions::default();
+/// opts.option_name_chars = Some(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+/// assert!(configster::parse_str_with("log.level = 3", &opts).is_ok());
+/// assert!(configster::parse_str_with("log-level = 3", &opts).is_err());
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ParseOptions<'a> {
+    /// The delimiter for the attribute list of the primary value. Defaults to
+    /// [DEFAULT_ATTR_DELIMIT_CHAR](constant.DEFAULT_ATTR_DELIMIT_CHAR.html).
+    pub attr_delimiter: Delimiter,
+    /// If set, every character in an option name must satisfy this predicate,
+    /// otherwise parsing fails with
+    /// [ParseError::InvalidOptionName](enum.ParseError.html#variant.InvalidOptionName).
+    pub option_name_chars: Option<fn(char) -> bool>,
+    /// When true, an option name containing whitespace is an error
+    /// ([ParseError::WhitespaceInOptionName](enum.ParseError.html#variant.WhitespaceInOptionName)).
+    /// By default the option is named "InvalidOption_on_Line<n>" instead.
+    pub reject_invalid_options: bool,
+    /// When true, a line with whitespace in the option name (e.g. a construct
+    /// of a later version of the format) is kept exactly as it is, as an
+    /// [Entry::Raw](enum.Entry.html#variant.Raw), so that the entries can be
+    /// written back unchanged. The functions that only return options skip
+    /// such lines.
+    pub raw_invalid_lines: bool,
+    /// The string separating an option from its value; defaults to "=". It
+    /// may be longer than one character (e.g. "=>" or ":=").
+    pub separator: &'a str,
+    /// When true, a line without the separator is split into the option and the value
+    /// at the first whitespace ("listen 0.0.0.0"). By default the whole line is
+    /// the option.
+    pub whitespace_separator: bool,
+    /// When true, the empty attributes produced by consecutive delimiters
+    /// ("a,, ,b") are dropped. By default they are kept as empty strings.
+    pub collapse_delimiters: bool,
+    /// If set, every attribute is converted to this case (the primary value
+    /// is left as it is).
+    pub normalize_attributes: Option<Case>,
+    /// The options expected in the configuration, for
+    /// [on_unknown](#structfield.on_unknown).
+    pub known_options: &'a [&'a str],
+    /// If set, called with the name and line number of each option that isn't
+    /// in [known_options](#structfield.known_options), as it is parsed.
+    pub on_unknown: Option<OnUnknown<'a>>,
+    /// If set, called with the name and line number of each section header
+    /// as it is parsed (e.g. to show progress while a large file loads).
+    pub on_section: Option<OnSection<'a>>,
+    /// When true, lines of the form `include <path>`,
+    /// `include_if_exists <path>` and `include_env <VAR>` are include
+    /// directives. The functions that parse files replace them with the
+    /// options of the included file (a relative path is relative to the
+    /// directory of the including file); `include_if_exists` skips a missing
+    /// file and `include_env` skips an unset variable. Strings and readers
+    /// have no location to include from, so the directives are dropped there
+    /// (the entries parse functions return them as
+    /// [Entry::Include](enum.Entry.html#variant.Include)). With the `glob`
+    /// feature, the path may be a pattern (`include conf.d/*.conf`), which
+    /// includes the matching files in sorted order.
+    pub includes: bool,
+    /// The number of levels of included files allowed below the file being
+    /// parsed (16 by default); a deeper include is an error.
+    pub max_include_depth: usize,
+    /// When true, a line starting with '@' is a
+    /// [Directive](struct.Directive.html) rather than an option; see
+    /// [parse_str_with_directives](fn.parse_str_with_directives.html).
+    pub directives: bool,
+    /// When true, a '#' preceded by whitespace starts a comment running to
+    /// the end of the line ("port = 80 # the default"), while a '#' directly
+    /// following another character is kept ("color=#fff"). `\#` is a literal
+    /// '#' (`say \#hashtag`), and `\\#` a backslash followed by a comment. By
+    /// default only lines starting with '#' are comments.
+    pub comment_requires_space: bool,
+    /// If set, the text between these open and close markers (e.g. `("/*",
+    /// "*/")`) is a comment, which may span several lines. A comment opened
+    /// inside another one is an error.
+    pub block_comments: Option<(&'a str, &'a str)>,
+    /// The kind of value of each option; an option that isn't in the schema
+    /// is split as a [Kind::List](enum.Kind.html#variant.List).
+    pub schema: BTreeMap<String, Kind>,
+    /// When true, the lines following an option that are indented more than
+    /// it and don't contain the separator continue its value; they are joined
+    /// to the option line with spaces before the value is split. Off by
+    /// default, since an indented line is otherwise an option of its own.
+    pub folded_values: bool,
+    /// When true, a value of the form `<<MARKER` starts a block value: the
+    /// lines that follow, up to a line consisting of exactly `MARKER`, are
+    /// joined (newlines preserved) into the primary value. Off by default,
+    /// since such a value is otherwise taken as it is.
+    pub block_values: bool,
+    /// When true, repeated attributes of an option are removed, keeping the
+    /// first occurrence; see [Value::dedup_attributes](struct.Value.html#method.dedup_attributes).
+    pub dedup_attributes: bool,
+    /// When true, a line whose indentation has both tabs and spaces is an
+    /// error ([ParseError::MixedIndent](enum.ParseError.html#variant.MixedIndent)).
+    pub reject_mixed_indent: bool,
+    /// When true, a delimiter inside brackets ("[]" or "{}") doesn't split the
+    /// value, so that "matrix = [1, 2], x" has the primary value "[1, 2]".
+    /// Unbalanced brackets are an error
+    /// ([ParseError::UnbalancedBrackets](enum.ParseError.html#variant.UnbalancedBrackets)).
+    pub bracket_values: bool,
+    /// If set, called with the primary value of each option as it is parsed
+    /// (e.g. to remove quotes or expand variables); the value it returns is
+    /// the one stored.
+    pub value_transform: Option<ValueTransform<'a>>,
+    /// When true, [value_transform](#structfield.value_transform) is also
+    /// applied to each attribute.
+    pub transform_attributes: bool,
+    /// When false, the whitespace around an option name (its indentation and
+    /// any whitespace before the separator) is kept as part of the name. The
+    /// name is still checked for whitespace inside it; see
+    /// [reject_invalid_options](#structfield.reject_invalid_options). True by
+    /// default.
+    pub trim_keys: bool,
+    /// When true, an option name may be put in double quotes, so that it can
+    /// contain whitespace and the separator (`"key with spaces" = value`);
+    /// the quotes are removed from the stored name, and inside them a
+    /// backslash escapes the next character.
+    pub quoted_keys: bool,
+    /// When true, the primary value and each attribute may be put in double
+    /// quotes, so that they can contain the attribute delimiter and keep the
+    /// whitespace around them (`font = "Noto, Bold", " x "`); the quotes are
+    /// removed, and inside them a backslash escapes the next character. This
+    /// reads back the quoting of
+    /// [Value::join_attributes](struct.Value.html#method.join_attributes) and
+    /// [write_to](fn.write_to.html).
+    pub quoted_values: bool,
+    /// If set, called for each backslash escape other than `\"` and `\\`
+    /// inside a quoted [value](#structfield.quoted_values) or
+    /// [option name](#structfield.quoted_keys), with the escaped character
+    /// and the line number. The character it returns replaces the escape;
+    /// with `None`, the escape is kept literally (`\X`). Without the
+    /// callback, an escaped character stands for itself.
+    pub on_unknown_escape: Option<OnUnknownEscape<'a>>,
+    /// When true, [parse_str_with_warnings](fn.parse_str_with_warnings.html)
+    /// also warns about option names with whitespace before them or before
+    /// the separator ("  max_users = 30"), which is otherwise trimmed
+    /// silently; see [Warning::KeyWhitespace](enum.Warning.html#variant.KeyWhitespace).
+    pub warn_key_whitespace: bool,
+    /// If set, an option with more attributes than this is an error
+    /// ([ParseError::TooManyAttributes](enum.ParseError.html#variant.TooManyAttributes)),
+    /// which bounds the memory a single line of untrusted input can use.
+    pub max_attributes_per_option: Option<usize>,
+    /// If set, every option name must start with this prefix (e.g. "app."),
+    /// otherwise parsing fails with
+    /// [ParseError::MissingPrefix](enum.ParseError.html#variant.MissingPrefix).
+    pub required_prefix: Option<&'a str>,
+    /// When true, the [required_prefix](#structfield.required_prefix) is
+    /// removed from the stored option names, so that "app.port" is stored as
+    /// "port".
+    pub strip_required_prefix: bool,
+    /// The options whose values are paths, for
+    /// [resolve_paths](#structfield.resolve_paths).
+    pub path_options: &'a [&'a str],
+    /// When true, the functions that parse files (through
+    /// [parse_file_with](fn.parse_file_with.html)) replace the primary value
+    /// of each option in [path_options](#structfield.path_options) with the
+    /// absolute, normalized path it names, relative to the directory of the
+    /// file it is in. Symbolic links are resolved for a path that exists.
+    pub resolve_paths: bool,
+    /// When true, a resolved path that doesn't exist is an error
+    /// ([ParseError::MissingPath](enum.ParseError.html#variant.MissingPath)).
+    pub require_paths: bool,
+}
+
+impl ParseOptions<'_> {
+    /// Returns the default options with the given attribute delimiter, which
+    /// can be a `char` or a `&str`.
+    pub fn with_delimiter(attr_delimiter: impl Into<Delimiter>) -> Self {
+        Self {
+            attr_delimiter: attr_delimiter.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the default options with a schema, so that the attributes
+    /// are only split for the options that are lists.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use configster::{Kind, ParseOptions};
+    ///
+    /// let schema = vec![("greeting".to_string(), Kind::Scalar)].into_iter().collect();
+    /// let opts = ParseOptions::with_schema(schema);
+    /// let config_vec = configster::parse_str_with("greeting = Hello, World\ncolors = red, blue", &opts).unwrap();
+    /// assert_eq!(config_vec[0].value.primary, "Hello, World");
+    /// assert_eq!(config_vec[1].value.attributes, vec!["blue"]);
+    /// ```
+    pub fn with_schema(schema: BTreeMap<String, Kind>) -> Self {
+        Self {
+            schema,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for ParseOptions<'_> {
+    fn default() -> Self {
+        Self {
+            attr_delimiter: Delimiter::Char(DEFAULT_ATTR_DELIMIT_CHAR),
+            option_name_chars: None,
+            separator: "=",
+            reject_invalid_options: false,
+            raw_invalid_lines: false,
+            whitespace_separator: false,
+            collapse_delimiters: false,
+            normalize_attributes: None,
+            known_options: &[],
+            on_unknown: None,
+            on_section: None,
+            includes: false,
+            max_include_depth: 16,
+            directives: false,
+            comment_requires_space: false,
+            block_comments: None,
+            schema: BTreeMap::new(),
+            folded_values: false,
+            block_values: false,
+            dedup_attributes: false,
+            reject_mixed_indent: false,
+            bracket_values: false,
+            value_transform: None,
+            transform_attributes: false,
+            trim_keys: true,
+            quoted_keys: false,
+            quoted_values: false,
+            on_unknown_escape: None,
+            warn_key_whitespace: false,
+            max_attributes_per_option: None,
+            required_prefix: None,
+            strip_required_prefix: false,
+            path_options: &[],
+            resolve_paths: false,
+            require_paths: false,
+        }
+    }
+}
+
+#[test]
+fn test_delimiter_split() {
+    let d = Delimiter::from("::");
+    assert_eq!(d.split_once("a :: b::c"), Some(("a ", " b::c")));
+    assert_eq!(d.split(" b::c:d").collect::<Vec<_>>(), vec![" b", "c:d"]);
+    assert_eq!(d.split_once("a : b"), None);
+
+    let d = Delimiter::from("");
+    assert_eq!(d.split_once("a, b"), None);
+
+    let d = Delimiter::Whitespace;
+    assert_eq!(d.split_once(" a  b\tc "), Some(("a", " b\tc")));
+    assert_eq!(d.split(" b\tc ").collect::<Vec<_>>(), vec!["b", "c"]);
+    assert_eq!(d.split_once("a"), None);
+
+    let d = Delimiter::from(';');
+    assert_eq!(d.split_once("a;b;"), Some(("a", "b;")));
+    assert_eq!(d.split("a;b;").collect::<Vec<_>>(), vec!["a", "b", ""]);
+    assert_eq!(d.split("").collect::<Vec<_>>(), vec![""]);
+
+    let d = Delimiter::from('→');
+    assert_eq!(d.split_once("a→b→c"), Some(("a", "b→c")));
+    assert_eq!(d.split("b→c").collect::<Vec<_>>(), vec!["b", "c"]);
+}
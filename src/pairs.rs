@@ -0,0 +1,62 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::OptionProperties;
+
+/// Returns the options as (option, primary value) pairs, for a flat
+/// configuration; the attributes and sections are dropped.
+///
+/// # Examples
+///
+/// ```
+/// let config_vec = configster::parse_str("max_users = 30\ncolor = blue, light\nDelayOff", ',').unwrap();
+/// let pairs = configster::to_pairs(&config_vec);
+/// assert_eq!(pairs[1], ("color".to_string(), "blue".to_string()));
+/// assert_eq!(pairs[2], ("DelayOff".to_string(), "".to_string()));
+/// ```
+pub fn to_pairs(vec: &[OptionProperties]) -> Vec<(String, String)> {
+    vec.iter()
+        .map(|o| (o.option.clone(), o.value.primary.clone()))
+        .collect()
+}
+
+/// Builds options from (option, primary value) pairs, e.g. taken from the
+/// environment. The values are used as they are, without splitting them into
+/// attributes, and the options aren't in any section.
+///
+/// # Examples
+///
+/// ```
+/// let pairs = vec![("max_users".to_string(), "30".to_string())];
+/// let config_vec = configster::from_pairs(&pairs);
+/// assert_eq!(config_vec[0].option, "max_users");
+/// assert_eq!(config_vec[0].value.primary, "30");
+/// ```
+pub fn from_pairs(pairs: &[(String, String)]) -> Vec<OptionProperties> {
+    pairs
+        .iter()
+        .map(|(option, primary)| OptionProperties::new(option.clone(), primary.clone(), Vec::new()))
+        .collect()
+}
+
+#[test]
+fn test_pairs() {
+    let config_vec = crate::parse_str("a = 1, x\n[s]\nb = 2, 3\na = 4", ',').unwrap();
+    let pairs = to_pairs(&config_vec);
+    let expected: Vec<(String, String)> = [("a", "1"), ("b", "2"), ("a", "4")]
+        .iter()
+        .map(|&(o, p)| (o.into(), p.into()))
+        .collect();
+    assert_eq!(pairs, expected);
+
+    let config_vec = from_pairs(&pairs);
+    assert_eq!(to_pairs(&config_vec), pairs);
+    assert!(config_vec
+        .iter()
+        .all(|o| o.section.is_none() && o.value.attributes.is_empty()));
+
+    // A value with a delimiter isn't split
+    let pairs = [("a".into(), "1, 2".into())];
+    assert_eq!(from_pairs(&pairs)[0].value.primary, "1, 2");
+    assert!(from_pairs(&[]).is_empty());
+}
@@ -0,0 +1,1844 @@
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter::{Enumerate, Peekable};
+
+use crate::entry::collect_sections;
+use crate::{
+    Case, Comment, Directive, Entry, Include, IncludeKind, Kind, OptionProperties, ParseError,
+    ParseOptions, Section,
+};
+
+/// Parses configuration data held in a string. Parsing behaves the same as
+/// [parse_file](fn.parse_file.html).
+///
+/// # Examples
+///
+/// ```
+/// let config_vec = configster::parse_str("max_users = 30", ',').unwrap();
+/// assert_eq!(config_vec[0].option, "max_users");
+/// ```
+pub fn parse_str(s: &str, attr_delimit_char: char) -> Result<Vec<OptionProperties>, ParseError> {
+    parse_str_with(s, &ParseOptions::with_delimiter(attr_delimit_char))
+}
+
+/// Like [parse_str](fn.parse_str.html), with the parsing behavior set by
+/// [ParseOptions](struct.ParseOptions.html).
+pub fn parse_str_with(s: &str, opts: &ParseOptions) -> Result<Vec<OptionProperties>, ParseError> {
+    only_options(LineParser::new(s.lines().map(Ok), opts)).collect()
+}
+
+/// Parses a single option line (e.g. a `--set "key = value, a, b"` command
+/// line argument) with the same rules as the lines of a file. Returns `None`
+/// if the line is blank or a comment (or a section header). If `s` has
+/// several lines, only the first option is returned.
+///
+/// # Examples
+///
+/// ```
+/// let opt_props = configster::parse_option("max_users = 30, soft", ',').unwrap().unwrap();
+/// assert_eq!(opt_props.option, "max_users");
+/// assert_eq!(opt_props.value.attributes, vec!["soft"]);
+/// assert_eq!(configster::parse_option("# max_users = 30", ',').unwrap(), None);
+/// ```
+pub fn parse_option(
+    s: &str,
+    attr_delimit_char: char,
+) -> Result<Option<OptionProperties>, ParseError> {
+    let opts = ParseOptions::with_delimiter(attr_delimit_char);
+    let first = only_options(LineParser::new(s.lines().map(Ok), &opts)).next();
+    first.transpose()
+}
+
+/// Like [parse_str_with](fn.parse_str_with.html), but returns every line as an
+/// [Entry](enum.Entry.html), including comments, blank lines and section
+/// headers.
+///
+/// # Examples
+///
+/// ```
+/// use configster::{Entry, ParseOptions};
+///
+/// let entries = configster::parse_str_entries("# Max. users\nmax_users = 30", &ParseOptions::default()).unwrap();
+/// match &entries[0] {
+///     Entry::Comment(c) => assert_eq!(c.text, "Max. users"),
+///     _ => unreachable!(),
+/// }
+/// ```
+pub fn parse_str_entries(s: &str, opts: &ParseOptions) -> Result<Vec<Entry>, ParseError> {
+    LineParser::new(s.lines().map(Ok), opts).collect()
+}
+
+/// Like [parse_str_with](fn.parse_str_with.html), but returns the options
+/// grouped into the blocks under each section header, as
+/// [parse_file_structured](fn.parse_file_structured.html) does.
+///
+/// # Examples
+///
+/// ```
+/// use configster::ParseOptions;
+///
+/// let sections = configster::parse_str_structured("debug\n[db]\nport = 5432", &ParseOptions::default()).unwrap();
+/// assert_eq!(sections.len(), 2);
+/// assert_eq!(sections[1].name.as_deref(), Some("db"));
+/// assert_eq!(sections[1].options[0].option, "port");
+/// ```
+pub fn parse_str_structured(s: &str, opts: &ParseOptions) -> Result<Vec<Section>, ParseError> {
+    collect_sections(LineParser::new(s.lines().map(Ok), opts))
+}
+
+/// Keeps only the options from a sequence of parsed entries.
+pub(crate) fn only_options<I>(
+    entries: I,
+) -> impl Iterator<Item = Result<OptionProperties, ParseError>>
+where
+    I: Iterator<Item = Result<Entry, ParseError>>,
+{
+    entries.filter_map(|e| match e {
+        Ok(Entry::Option(opt_props)) => Some(Ok(opt_props)),
+        Ok(_) => None,
+        Err(e) => Some(Err(e)),
+    })
+}
+
+/// Parses entries from a sequence of lines; the functions that parse files,
+/// readers and strings all feed their lines through this iterator. The line
+/// parsing itself only needs `alloc`.
+pub(crate) struct LineParser<'o, 'a, I: Iterator> {
+    lines: Peekable<Enumerate<I>>,
+    opts: &'o ParseOptions<'a>,
+    section: Option<String>,
+    line: usize,
+    /// The line where the block comment that is still open started
+    comment_start: Option<usize>,
+    /// Whether the name of the option returned last had whitespace around
+    /// it, with `warn_key_whitespace` set
+    key_whitespace: bool,
+}
+
+impl<'o, 'a, I, S> LineParser<'o, 'a, I>
+where
+    I: Iterator<Item = Result<S, ParseError>>,
+    S: AsRef<str>,
+{
+    pub(crate) fn new(lines: I, opts: &'o ParseOptions<'a>) -> Self {
+        Self {
+            lines: lines.enumerate().peekable(),
+            opts,
+            section: None,
+            line: 0,
+            comment_start: None,
+            key_whitespace: false,
+        }
+    }
+
+    /// Returns the line number of the entry returned last (the first line, for
+    /// a block value).
+    pub(crate) fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns true if the name of the option returned last had whitespace
+    /// around it in the line (only checked with
+    /// [ParseOptions::warn_key_whitespace](struct.ParseOptions.html#structfield.warn_key_whitespace)).
+    pub(crate) fn key_whitespace(&self) -> bool {
+        self.key_whitespace
+    }
+
+    /// Parses a single line (and, for a block value, the lines that belong to
+    /// it).
+    fn parse(&mut self, line: &str, ln: usize) -> Result<Entry, ParseError> {
+        let line = strip_cr(line);
+        let raw = line;
+        // Blank lines have no indentation to speak of
+        if self.opts.reject_mixed_indent && !line.trim().is_empty() {
+            let indent = &line[..indentation(line)];
+            if indent.contains('\t') && indent.contains(' ') {
+                return Err(ParseError::MixedIndent { line: ln });
+            }
+        }
+        let uncommented;
+        let line = match self.opts.block_comments {
+            Some(markers) => match self.strip_block_comments(line, ln, markers)? {
+                Some((code, comment)) if code.trim().is_empty() => {
+                    return Ok(Entry::Comment(Comment {
+                        text: comment.trim().to_string(),
+                        raw: line.to_string(),
+                        level: 0,
+                    }));
+                }
+                Some((code, _)) => {
+                    uncommented = code;
+                    uncommented.as_str()
+                }
+                None => line,
+            },
+            None => line,
+        };
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            return Ok(Entry::Blank);
+        }
+        if trimmed.starts_with('#') {
+            let text = trimmed.trim_start_matches('#');
+            return Ok(Entry::Comment(Comment {
+                level: trimmed.len() - text.len(),
+                text: text.trim().to_string(),
+                raw: line.to_string(),
+            }));
+        }
+        if let Some(name) = section_header(line) {
+            if let Some(on_section) = &self.opts.on_section {
+                (on_section.get())(name, ln);
+            }
+            self.section = Some(name.to_string());
+            return Ok(Entry::Section(name.to_string()));
+        }
+        if self.opts.directives {
+            if let Some(rest) = trimmed.strip_prefix('@') {
+                let mut words = rest.split_whitespace();
+                return Ok(Entry::Directive(Directive {
+                    name: words.next().unwrap_or_default().to_string(),
+                    args: words.map(str::to_string).collect(),
+                    section: self.section.clone(),
+                    line: ln,
+                }));
+            }
+        }
+        if self.opts.includes {
+            if let Some(include) = include_directive(line, self.opts.separator) {
+                return Ok(Entry::Include(include));
+            }
+        }
+
+        let line = if self.opts.comment_requires_space {
+            strip_inline_comment(line)
+        } else {
+            Cow::Borrowed(line)
+        };
+        let line = line.as_ref();
+
+        // Parse the line, return the properties
+        let (mut parsed, mut invalid) = parse_line_or_invalid(line, self.opts, ln)?;
+        if self.opts.raw_invalid_lines && invalid {
+            return Ok(Entry::Raw(raw.to_string()));
+        }
+        let block = self.opts.block_values && heredoc_marker(&parsed.1, &parsed.2).is_some();
+        if self.opts.folded_values && !block {
+            if let Some(folded) = self.fold(line) {
+                let (folded, folded_invalid) = parse_line_or_invalid(&folded, self.opts, ln)?;
+                parsed = folded;
+                invalid = folded_invalid;
+            }
+        }
+        let (option, mut primary_value, mut attr_vec) = parsed;
+        if self.opts.warn_key_whitespace {
+            self.key_whitespace = key_has_whitespace(line, self.opts);
+        }
+
+        if let Some(marker) =
+            heredoc_marker(&primary_value, &attr_vec).filter(|_| self.opts.block_values)
+        {
+            let marker = marker.to_string();
+            let mut block: Vec<String> = Vec::new();
+            let mut terminated = false;
+            for (_, l) in self.lines.by_ref() {
+                let l = l?;
+                let l = strip_cr(l.as_ref());
+                if l == marker {
+                    terminated = true;
+                    break;
+                }
+                block.push(l.to_string());
+            }
+            if !terminated {
+                return Err(ParseError::UnterminatedBlock { option, line: ln });
+            }
+            primary_value = block.join("\n");
+        }
+
+        if let Some(transform) = &self.opts.value_transform {
+            let mut transform = transform.get();
+            primary_value = transform(&primary_value);
+            if self.opts.transform_attributes {
+                for attr in &mut attr_vec {
+                    *attr = transform(attr);
+                }
+            }
+        }
+
+        if let Some(on_unknown) = &self.opts.on_unknown {
+            if !self.opts.known_options.contains(&option.as_str()) {
+                (on_unknown.get())(&option, ln);
+            }
+        }
+
+        let mut opt_props = OptionProperties::new(option, primary_value, attr_vec);
+        opt_props.section = self.section.clone();
+        opt_props.invalid = invalid;
+        if self.opts.dedup_attributes {
+            opt_props.value.dedup_attributes();
+        }
+        Ok(Entry::Option(opt_props))
+    }
+
+    /// Returns the option line joined (with spaces) with the lines following
+    /// it that continue its value, or `None` if there are none. A line
+    /// continues the value if it is indented more than the option and doesn't
+    /// contain the separator.
+    fn fold(&mut self, line: &str) -> Option<String> {
+        let separator = self.opts.separator;
+        if separator.is_empty() || !line.contains(separator) {
+            return None;
+        }
+        let indent = indentation(line);
+        let mut folded: Option<String> = None;
+        while let Some((_, Ok(next))) = self.lines.peek() {
+            let next = strip_cr(next.as_ref());
+            let trimmed = next.trim();
+            if trimmed.is_empty()
+                || trimmed.starts_with('#')
+                || indentation(next) <= indent
+                || next.contains(separator)
+            {
+                break;
+            }
+            let text = trimmed.to_string();
+            let folded = folded.get_or_insert_with(|| line.trim_end().to_string());
+            folded.push(' ');
+            folded.push_str(&text);
+            self.lines.next();
+        }
+        folded
+    }
+
+    /// Removes the block comments (between the `open` and `close` markers)
+    /// from a line, keeping track of a comment that continues on the next
+    /// lines. Returns the rest of the line and the text of the comments, or
+    /// `None` if the line has no comments.
+    fn strip_block_comments(
+        &mut self,
+        line: &str,
+        ln: usize,
+        (open, close): (&str, &str),
+    ) -> Result<Option<(String, String)>, ParseError> {
+        if self.comment_start.is_none() && !line.contains(open) {
+            return Ok(None);
+        }
+        let mut code = String::new();
+        let mut comment = String::new();
+        let mut rest = line;
+        loop {
+            if self.comment_start.is_some() {
+                let close_at = rest.find(close);
+                if let Some(open_at) = rest.find(open) {
+                    if close_at.is_none_or(|c| open_at < c) {
+                        return Err(ParseError::NestedBlockComment { line: ln });
+                    }
+                }
+                match close_at {
+                    Some(c) => {
+                        comment.push_str(&rest[..c]);
+                        rest = &rest[c + close.len()..];
+                        self.comment_start = None;
+                    }
+                    None => {
+                        comment.push_str(rest);
+                        break;
+                    }
+                }
+            } else {
+                match rest.find(open) {
+                    Some(o) => {
+                        code.push_str(&rest[..o]);
+                        rest = &rest[o + open.len()..];
+                        self.comment_start = Some(ln);
+                    }
+                    None => {
+                        code.push_str(rest);
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(Some((code, comment)))
+    }
+}
+
+impl<I, S> Iterator for LineParser<'_, '_, I>
+where
+    I: Iterator<Item = Result<S, ParseError>>,
+    S: AsRef<str>,
+{
+    type Item = Result<Entry, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (line_num, line) = match self.lines.next() {
+            Some(next) => next,
+            None => {
+                let line = self.comment_start.take()?;
+                return Some(Err(ParseError::UnterminatedBlockComment { line }));
+            }
+        };
+        self.line = line_num + 1;
+        Some(line.and_then(|l| self.parse(l.as_ref(), line_num + 1)))
+    }
+}
+
+/// Removes the '\r' left at the end of a line when the last line of CRLF
+/// data has no line ending (BufRead::lines() only strips a full "\r\n").
+fn strip_cr(line: &str) -> &str {
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+/// Removes an inline comment: a '#' preceded by whitespace, and the rest of
+/// the line. `\#` is a literal '#', and `\\#` a backslash followed by a
+/// comment.
+fn strip_inline_comment(line: &str) -> Cow<'_, str> {
+    if !line.contains('\\') {
+        let mut after_space = false;
+        for (i, c) in line.char_indices() {
+            if c == '#' && after_space {
+                return Cow::Borrowed(&line[..i]);
+            }
+            after_space = c.is_whitespace();
+        }
+        return Cow::Borrowed(line);
+    }
+
+    let mut out = String::with_capacity(line.len());
+    let mut after_space = false;
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with("\\\\#") {
+            out.push('\\');
+            break;
+        }
+        if let Some(r) = rest.strip_prefix("\\#") {
+            out.push('#');
+            after_space = false;
+            rest = r;
+            continue;
+        }
+        if c == '#' && after_space {
+            break;
+        }
+        out.push(c);
+        after_space = c.is_whitespace();
+        rest = &rest[c.len_utf8()..];
+    }
+    Cow::Owned(out)
+}
+
+/// Returns the number of bytes of leading whitespace.
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Returns the section name if the line is a section header ("[name]").
+fn section_header(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let name = line.strip_prefix('[')?.strip_suffix(']')?;
+    Some(name.trim())
+}
+
+/// Returns true if the option name on the line has whitespace before it, or
+/// between it and the separator.
+fn key_has_whitespace(line: &str, opts: &ParseOptions) -> bool {
+    let key = match opts.separator {
+        "" => None,
+        sep => line.find(sep).map(|i| &line[..i]),
+    };
+    match key {
+        Some(key) => key.trim() != key,
+        // The name ends at the first whitespace, or is the whole line
+        None if opts.whitespace_separator => line.starts_with(char::is_whitespace),
+        None => line.trim() != line,
+    }
+}
+
+/// Returns the unquoted option name and the value if the line starts with a
+/// double-quoted name followed by the separator (or nothing, for a flag).
+/// Inside the quotes, a backslash escapes the next character (see
+/// [push_escaped]).
+fn quoted_key<'l>(line: &'l str, opts: &ParseOptions, ln: usize) -> Option<(String, &'l str)> {
+    let mut chars = line.strip_prefix('"')?.char_indices();
+    let mut option = String::new();
+    let end = loop {
+        match chars.next()? {
+            (_, '\\') => push_escaped(&mut option, chars.next()?.1, opts, ln),
+            // The index after the closing quote, in `line`
+            (i, '"') => break i + 2,
+            (_, c) => option.push(c),
+        }
+    };
+    let after = &line[end..];
+    let rest = after.trim_start();
+    if rest.is_empty() {
+        return Some((option, ""));
+    }
+    let value = match opts.separator {
+        "" => None,
+        sep => rest.strip_prefix(sep),
+    };
+    match value {
+        Some(value) => Some((option, value.trim())),
+        None if opts.whitespace_separator && rest.len() < after.len() => Some((option, rest)),
+        None => None,
+    }
+}
+
+/// Returns the include directive if the line is one ("include <path>").
+fn include_directive(line: &str, separator: &str) -> Option<Include> {
+    let (keyword, target) = line.trim().split_once(char::is_whitespace)?;
+    let kind = match keyword {
+        "include" => IncludeKind::File,
+        "include_if_exists" => IncludeKind::IfExists,
+        "include_env" => IncludeKind::Env,
+        _ => return None,
+    };
+    // "include = ..." is an option named "include"
+    let target = target.trim();
+    if !separator.is_empty() && target.starts_with(separator) {
+        return None;
+    }
+    Some(Include {
+        kind,
+        target: target.to_string(),
+    })
+}
+
+/// Returns the end marker if the value starts a block value ("<<MARKER").
+fn heredoc_marker<'a>(primary: &'a str, attributes: &[String]) -> Option<&'a str> {
+    if !attributes.is_empty() {
+        return None;
+    }
+    let marker = primary.strip_prefix("<<")?;
+    if marker.is_empty() || marker.chars().any(char::is_whitespace) {
+        return None;
+    }
+    Some(marker)
+}
+
+/// The option name, primary value and attributes of a line.
+type ParsedLine = (String, String, Vec<String>);
+
+/// Like `parse_line`, but an invalid line (with whitespace in the option
+/// name) gives an option named "InvalidOption_on_Line<n>" unless the options
+/// reject it; the flag returned with the properties is true for it.
+fn parse_line_or_invalid(
+    l: &str,
+    opts: &ParseOptions,
+    ln: usize,
+) -> Result<(ParsedLine, bool), ParseError> {
+    match parse_line(l, opts, ln) {
+        Err(ParseError::WhitespaceInOptionName { .. }) if !opts.reject_invalid_options => {
+            Ok(((invalid_option(ln), "".to_string(), vec![]), true))
+        }
+        parsed => Ok((parsed?, false)),
+    }
+}
+
+/// Returns the properties of the option, derived from
+/// a line in the configuration file. Whitespace in the option name is an
+/// error here; see `parse_line_or_invalid`.
+fn parse_line(l: &str, opts: &ParseOptions, ln: usize) -> Result<ParsedLine, ParseError> {
+    let line = l.trim();
+    if line.is_empty() || line.as_bytes()[0] == b'#' {
+        return Ok(("".to_string(), "".to_string(), vec![]));
+    }
+
+    if opts.quoted_keys {
+        if let Some((option, value)) = quoted_key(line, opts, ln) {
+            let option = check_prefix(option, opts, ln)?;
+            let (primary_value, attr_vec) = split_value(&option, value, opts, ln)?;
+            return Ok((option, primary_value, attr_vec));
+        }
+    }
+
+    // The position and length of the separator
+    let mut sep = match opts.separator {
+        "" => None,
+        s => line.find(s).map(|i| (i, s.len())),
+    };
+    if sep.is_none() && opts.whitespace_separator {
+        sep = line
+            .find(char::is_whitespace)
+            .map(|i| (i, line[i..].chars().next().map_or(1, char::len_utf8)));
+    }
+    let (name, value) = match sep {
+        Some((i, sep_len)) => (line[..i].trim_end(), line[i + sep_len..].trim()),
+        None => (line, ""),
+    };
+
+    // The column of the character at byte index `pos` of the option name
+    let leading = l.len() - l.trim_start().len();
+    let col = |pos: usize| l[..leading + pos].chars().count() + 1;
+
+    if let Some(valid) = opts.option_name_chars {
+        if let Some((pos, _)) = name.char_indices().find(|(_, c)| !valid(*c)) {
+            return Err(ParseError::InvalidOptionName {
+                option: name.to_string(),
+                line: ln,
+                col: col(pos),
+            });
+        }
+    }
+
+    // An Equal sign is required after 'Option'; spaces within 'Option' is invalid.
+    if let Some(pos) = name.find(char::is_whitespace) {
+        return Err(ParseError::WhitespaceInOptionName {
+            option: name.to_string(),
+            line: ln,
+            col: col(pos),
+        });
+    }
+
+    // Without trimming, the option is everything before the separator
+    let option = match sep {
+        _ if opts.trim_keys => name.to_string(),
+        Some((i, _)) => l[..leading + i].to_string(),
+        None => l.to_string(),
+    };
+    let option = check_prefix(option, opts, ln)?;
+
+    let (primary_value, attr_vec) = split_value(&option, value, opts, ln)?;
+    Ok((option, primary_value, attr_vec))
+}
+
+/// The name given to an invalid option on line `ln`.
+fn invalid_option(ln: usize) -> String {
+    format!("{}_on_Line{}", "InvalidOption", ln)
+}
+
+/// Checks that `option` starts with the required prefix (after any whitespace
+/// that wasn't trimmed), and strips it if set in the options.
+fn check_prefix(option: String, opts: &ParseOptions, ln: usize) -> Result<String, ParseError> {
+    let prefix = match opts.required_prefix {
+        Some(prefix) => prefix,
+        None => return Ok(option),
+    };
+    let name = option.trim_start();
+    match name.strip_prefix(prefix) {
+        Some(rest) if opts.strip_required_prefix => {
+            let leading = option.len() - name.len();
+            Ok(format!("{}{}", &option[..leading], rest))
+        }
+        Some(_) => Ok(option),
+        None => Err(ParseError::MissingPrefix {
+            option: option.trim().to_string(),
+            prefix: prefix.to_string(),
+            line: ln,
+        }),
+    }
+}
+
+/// Splits the value of `option` (on line `ln`) into the primary value and the
+/// attributes.
+pub(crate) fn split_value(
+    option: &str,
+    value: &str,
+    opts: &ParseOptions,
+    ln: usize,
+) -> Result<(String, Vec<String>), ParseError> {
+    // The value is split without intermediate collections (except to join
+    // the pieces inside brackets or quotes); only the primary value and the
+    // attributes that are kept are allocated.
+    // The brackets are checked in each group, since they don't count
+    // inside quotes
+    let check = |group: &str| match opts.bracket_values && !is_quoted(group, opts) {
+        true => check_brackets(group, ln),
+        false => Ok(()),
+    };
+    let split = match opts.schema.get(option) {
+        Some(Kind::Scalar) => None,
+        _ => opts.attr_delimiter.split_once(value),
+    };
+    if split.is_none() {
+        check(value)?;
+    }
+    // For a list without a primary value, every piece is an attribute
+    let items = opts.schema.get(option) == Some(&Kind::Items);
+    let (primary_value, attr_vec) = match split {
+        Some((primary, attributes)) if opts.bracket_values || opts.quoted_values => {
+            let pieces = core::iter::once(primary).chain(opts.attr_delimiter.split(attributes));
+            let groups = value_groups(value, pieces, opts);
+            groups.iter().try_for_each(|g| check(g))?;
+            let mut groups = groups.into_iter();
+            if items {
+                (String::new(), collect_attributes(groups, opts, ln))
+            } else {
+                let primary = groups.next().unwrap_or_default();
+                (
+                    unquote(primary.trim(), opts, ln),
+                    collect_attributes(groups, opts, ln),
+                )
+            }
+        }
+        Some((primary, attributes)) if items => {
+            let pieces = core::iter::once(primary).chain(opts.attr_delimiter.split(attributes));
+            (String::new(), collect_attributes(pieces, opts, ln))
+        }
+        Some((primary, attributes)) => (
+            primary.trim().to_string(),
+            collect_attributes(opts.attr_delimiter.split(attributes), opts, ln),
+        ),
+        None if items && !value.is_empty() => (
+            String::new(),
+            collect_attributes(core::iter::once(value), opts, ln),
+        ),
+        None => (unquote(value, opts, ln), Vec::new()),
+    };
+
+    if let Some(max) = opts.max_attributes_per_option {
+        if attr_vec.len() > max {
+            return Err(ParseError::TooManyAttributes {
+                option: option.to_string(),
+                line: ln,
+                max,
+            });
+        }
+    }
+
+    if opts.schema.get(option) == Some(&Kind::Numbers) && !value.is_empty() {
+        let values = core::iter::once(&primary_value).chain(&attr_vec);
+        for (index, v) in values.enumerate() {
+            if !v.parse::<f64>().is_ok_and(f64::is_finite) {
+                return Err(ParseError::NotANumber {
+                    option: option.to_string(),
+                    value: v.to_string(),
+                    index,
+                    line: ln,
+                });
+            }
+        }
+    }
+
+    Ok((primary_value, attr_vec))
+}
+
+/// Trims the attributes (of an option on line `ln`), and drops or converts
+/// them as set in the options.
+fn collect_attributes<'s>(
+    attributes: impl Iterator<Item = &'s str>,
+    opts: &ParseOptions,
+    ln: usize,
+) -> Vec<String> {
+    // One more than the limit is enough to tell that it is exceeded
+    let limit = opts
+        .max_attributes_per_option
+        .map_or(usize::MAX, |max| max.saturating_add(1));
+    attributes
+        .map(str::trim)
+        .filter(|a| !(a.is_empty() && opts.collapse_delimiters))
+        .take(limit)
+        .map(|a| {
+            let a = unquote(a, opts, ln);
+            match opts.normalize_attributes {
+                Some(Case::Lower) => a.to_lowercase(),
+                Some(Case::Upper) => a.to_uppercase(),
+                None => a,
+            }
+        })
+        .collect()
+}
+
+/// Removes the double quotes around a (trimmed) value and the backslashes
+/// escaping characters inside them, with
+/// [ParseOptions::quoted_values](struct.ParseOptions.html#structfield.quoted_values)
+/// set (see [push_escaped]). A value that isn't entirely in quotes is
+/// returned as it is.
+fn unquote(value: &str, opts: &ParseOptions, ln: usize) -> String {
+    if !opts.quoted_values {
+        return value.to_string();
+    }
+    let inner = match value.strip_prefix('"') {
+        Some(inner) => inner,
+        None => return value.to_string(),
+    };
+    let mut unquoted = String::with_capacity(inner.len());
+    let mut chars = inner.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, escaped)) => push_escaped(&mut unquoted, escaped, opts, ln),
+                None => break,
+            },
+            '"' if i + 1 == inner.len() => return unquoted,
+            '"' => break,
+            c => unquoted.push(c),
+        }
+    }
+    value.to_string()
+}
+
+/// Pushes the character escaped by a backslash inside quotes on line `ln`.
+/// `\"` and `\\` stand for the character; the other escapes go through
+/// [ParseOptions::on_unknown_escape](struct.ParseOptions.html#structfield.on_unknown_escape)
+/// if it is set.
+fn push_escaped(out: &mut String, escaped: char, opts: &ParseOptions, ln: usize) {
+    let on_unknown = match &opts.on_unknown_escape {
+        Some(on_unknown) if escaped != '"' && escaped != '\\' => on_unknown,
+        _ => return out.push(escaped),
+    };
+    let replacement = (on_unknown.get())(escaped, ln);
+    match replacement {
+        Some(c) => out.push(c),
+        None => {
+            out.push('\\');
+            out.push(escaped);
+        }
+    }
+}
+
+/// Checks that the brackets ("[]" and "{}") in the value are balanced.
+fn check_brackets(value: &str, ln: usize) -> Result<(), ParseError> {
+    let mut open: Vec<char> = Vec::new();
+    for c in value.chars() {
+        match c {
+            '[' => open.push(']'),
+            '{' => open.push('}'),
+            ']' | '}' if open.pop() != Some(c) => {
+                return Err(ParseError::UnbalancedBrackets { line: ln });
+            }
+            _ => {}
+        }
+    }
+    if open.is_empty() {
+        Ok(())
+    } else {
+        Err(ParseError::UnbalancedBrackets { line: ln })
+    }
+}
+
+/// Whether a group of pieces opens with a quote, with `quoted_values`
+fn is_quoted(group: &str, opts: &ParseOptions) -> bool {
+    opts.quoted_values && group.trim_start().starts_with('"')
+}
+
+/// Joins the pieces of `value` produced by splitting it at the delimiters
+/// back together where the delimiter is inside brackets (with
+/// `bracket_values`) or inside the quotes around a value (with
+/// `quoted_values`). The pieces must be slices of `value`, in order.
+fn value_groups<'s>(
+    value: &'s str,
+    pieces: impl Iterator<Item = &'s str>,
+    opts: &ParseOptions,
+) -> Vec<&'s str> {
+    let offset = |piece: &str| piece.as_ptr() as usize - value.as_ptr() as usize;
+    let mut groups = Vec::new();
+    let mut depth = 0;
+    let mut start = None;
+    let mut quoted = false;
+    for piece in pieces {
+        let mut chars = piece.chars();
+        if start.is_none() && is_quoted(piece, opts) {
+            quoted = true;
+            chars = piece.trim_start()[1..].chars();
+        }
+        let begin = *start.get_or_insert(offset(piece));
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if quoted => {
+                    chars.next();
+                }
+                '"' if quoted => quoted = false,
+                _ if quoted || !opts.bracket_values => {}
+                '[' | '{' => depth += 1,
+                ']' | '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        if depth == 0 && !quoted {
+            groups.push(&value[begin..offset(piece) + piece.len()]);
+            start = None;
+        }
+    }
+    // An unterminated quote runs to the end of the value
+    if let Some(begin) = start {
+        groups.push(&value[begin..]);
+    }
+    groups
+}
+
+#[test]
+fn test_parse_str_crlf() {
+    let lf = "option = Blue, light, shiny\n# comment\n\nscript = <<END\n echo\nEND\nDelayOff";
+    let crlf = lf.replace('\n', "\r\n");
+    let opts = ParseOptions {
+        block_values: true,
+        ..ParseOptions::default()
+    };
+    let parsed = parse_str_with(lf, &opts).unwrap();
+    assert_eq!(parsed[1].value.primary, " echo");
+    assert_eq!(parse_str_with(&crlf, &opts).unwrap(), parsed);
+
+    let crlf_terminated = crlf.clone() + "\r\n";
+    assert_eq!(parse_str_with(&crlf_terminated, &opts).unwrap(), parsed);
+
+    // The last line of the block ends with a lone '\r'
+    let config_vec = parse_str_with("script = <<END\r\necho\r\nEND\r", &opts).unwrap();
+    assert_eq!(config_vec[0].value.primary, "echo");
+}
+
+#[test]
+fn test_parse_option() {
+    let opt_props = parse_option("  DelayOff  ", ',').unwrap().unwrap();
+    assert_eq!(opt_props.option, "DelayOff");
+    assert_eq!(opt_props.section, None);
+    let opt_props = parse_option("colors = red; blue ;green", ';')
+        .unwrap()
+        .unwrap();
+    assert_eq!(opt_props.value.primary, "red");
+    assert_eq!(opt_props.value.attributes, vec!["blue", "green"]);
+    for s in ["", "   ", "# comment", "[section]"].iter() {
+        assert_eq!(parse_option(s, ',').unwrap(), None, "{:?}", s);
+    }
+    let opt_props = parse_option("a = 1\nb = 2", ',').unwrap().unwrap();
+    assert_eq!(opt_props.option, "a");
+    // Block values aren't enabled
+    let opt_props = parse_option("script = <<END", ',').unwrap().unwrap();
+    assert_eq!(opt_props.value.primary, "<<END");
+}
+
+#[test]
+fn test_sections() {
+    let config_vec = parse_str(
+        "level = 1\n[logging]\nlevel = 2\n  [ network ]  \n# comment\nlevel = 3",
+        ',',
+    )
+    .unwrap();
+    let sections: Vec<Option<&str>> = config_vec.iter().map(|o| o.section.as_deref()).collect();
+    assert_eq!(sections, vec![None, Some("logging"), Some("network")]);
+    assert_eq!(config_vec[2].value.primary, "3");
+}
+
+#[test]
+fn test_parse_str_entries() {
+    let data =
+        "# Users\n  ##  Max. users  \nmax_users = 30\n\n   \n[db]\nport = 5432 # not a comment";
+    let entries = parse_str_entries(data, &ParseOptions::default()).unwrap();
+    let mut port = OptionProperties::new(
+        "port".to_string(),
+        "5432 # not a comment".to_string(),
+        vec![],
+    );
+    port.section = Some("db".to_string());
+    assert_eq!(
+        entries,
+        vec![
+            Entry::Comment(Comment {
+                text: "Users".to_string(),
+                raw: "# Users".to_string(),
+                level: 1
+            }),
+            Entry::Comment(Comment {
+                text: "Max. users".to_string(),
+                raw: "  ##  Max. users  ".to_string(),
+                level: 2
+            }),
+            Entry::Option(OptionProperties::new(
+                "max_users".to_string(),
+                "30".to_string(),
+                vec![]
+            )),
+            Entry::Blank,
+            Entry::Blank,
+            Entry::Section("db".to_string()),
+            Entry::Option(port),
+        ]
+    );
+}
+
+#[test]
+fn test_on_unknown() {
+    use crate::Callback;
+    use alloc::boxed::Box;
+
+    let mut unknown: Vec<(String, usize)> = Vec::new();
+    let opts = ParseOptions {
+        known_options: &["max_users", "DelayOff"],
+        on_unknown: Some(Callback::new(Box::new(|name: &str, line| {
+            unknown.push((name.to_string(), line))
+        }))),
+        ..ParseOptions::default()
+    };
+    let config_vec = parse_str_with(
+        "max_users = 30\nmax_user = 3\n# colour = red\n\n[x]\ncolour = red\nDelayOff",
+        &opts,
+    )
+    .unwrap();
+    assert_eq!(config_vec.len(), 4);
+    drop(opts);
+    assert_eq!(
+        unknown,
+        vec![("max_user".to_string(), 2), ("colour".to_string(), 6)]
+    );
+}
+
+#[test]
+fn test_on_unknown_escape() {
+    use crate::Callback;
+    use alloc::boxed::Box;
+
+    let mut escapes: Vec<(char, usize)> = Vec::new();
+    let opts = ParseOptions {
+        quoted_values: true,
+        quoted_keys: true,
+        on_unknown_escape: Some(Callback::new(Box::new(|c, line| {
+            escapes.push((c, line));
+            match c {
+                'n' => Some('\n'),
+                't' => Some('\t'),
+                _ => None,
+            }
+        }))),
+        ..ParseOptions::default()
+    };
+    let data = r#"a = "x\ny", "\"\\\q"
+"k\tey" = 1
+"\z" = "\"""#;
+    let config_vec = parse_str_with(data, &opts).unwrap();
+    assert_eq!(config_vec[0].value.primary, "x\ny");
+    assert_eq!(config_vec[0].value.attributes, vec![r#""\\q"#]);
+    assert_eq!(config_vec[1].option, "k\tey");
+    assert_eq!(config_vec[2].option, r"\z");
+    assert_eq!(config_vec[2].value.primary, "\"");
+    drop(opts);
+    // '"' and '\' aren't unknown
+    assert_eq!(escapes, vec![('n', 1), ('q', 1), ('t', 2), ('z', 3)]);
+
+    // Without the callback, an escaped character stands for itself
+    let opts = ParseOptions {
+        quoted_values: true,
+        ..ParseOptions::default()
+    };
+    assert_eq!(
+        parse_str_with(r#"a = "x\ny""#, &opts).unwrap()[0]
+            .value
+            .primary,
+        "xny"
+    );
+}
+
+#[test]
+fn test_on_section() {
+    use crate::Callback;
+    use alloc::boxed::Box;
+
+    let mut sections: Vec<(String, usize)> = Vec::new();
+    let opts = ParseOptions {
+        on_section: Some(Callback::new(Box::new(|name: &str, line| {
+            sections.push((name.to_string(), line))
+        }))),
+        ..ParseOptions::default()
+    };
+    let entries =
+        parse_str_entries("a = 1\n[db]\nport = 1\n# [x]\n  [ web ]\n[db]", &opts).unwrap();
+    assert_eq!(entries.len(), 6);
+    drop(opts);
+    assert_eq!(
+        sections,
+        vec![
+            ("db".to_string(), 2),
+            ("web".to_string(), 5),
+            ("db".to_string(), 6)
+        ]
+    );
+}
+
+#[test]
+fn test_parse_line() {
+    // Test with no attributes
+    assert_eq!(
+        parse_line("Option = /home/foo", &ParseOptions::default(), 0).unwrap(),
+        ("Option".to_string(), "/home/foo".to_string(), vec![])
+    );
+
+    // Test with 5 attributes and several spaces
+    assert_eq!(
+        parse_line(
+            "Option=/home/foo , another  ,   test,1,2,3",
+            &ParseOptions::default(),
+            0
+        )
+        .unwrap(),
+        (
+            "Option".to_string(),
+            "/home/foo".to_string(),
+            vec![
+                "another".to_string(),
+                "test".to_string(),
+                "1".to_string(),
+                "2".to_string(),
+                "3".to_string()
+            ]
+        )
+    );
+
+    // Test with leading '#' sign
+    assert_eq!(
+        parse_line("#Option = /home/foo", &ParseOptions::default(), 0).unwrap(),
+        ("".to_string(), "".to_string(), vec![])
+    );
+
+    // Test with two attributes, a single space after the commas
+    assert_eq!(
+        parse_line(
+            "Option = /home/foo, removable, test",
+            &ParseOptions::default(),
+            0
+        )
+        .unwrap(),
+        (
+            "Option".to_string(),
+            "/home/foo".to_string(),
+            vec!["removable".to_string(), "test".to_string()]
+        )
+    );
+
+    // Test for blank line
+    assert_eq!(
+        parse_line("        ", &ParseOptions::default(), 0).unwrap(),
+        ("".to_string(), "".to_string(), vec![])
+    );
+
+    // Test for whitespace in Option
+    assert_eq!(
+        parse_line_or_invalid("Option  /home/foo", &ParseOptions::default(), 28).unwrap(),
+        (
+            (
+                "InvalidOption_on_Line28".to_string(),
+                "".to_string(),
+                vec![]
+            ),
+            true
+        )
+    );
+
+    // Test for '=' after Option has already been marked as invalid.
+    assert_eq!(
+        parse_line_or_invalid("Option  /home/foo = value", &ParseOptions::default(), 9).unwrap(),
+        (
+            ("InvalidOption_on_Line9".to_string(), "".to_string(), vec![]),
+            true
+        )
+    );
+}
+
+#[test]
+fn test_parse_line_equal_signs_in_value() {
+    // Only the first '=' separates the option from the value
+    assert_eq!(
+        parse_line("query = a=b&c=d", &ParseOptions::default(), 1).unwrap(),
+        ("query".to_string(), "a=b&c=d".to_string(), vec![])
+    );
+    assert_eq!(
+        parse_line("query = a=b, c=d, =e=", &ParseOptions::default(), 2).unwrap(),
+        (
+            "query".to_string(),
+            "a=b".to_string(),
+            vec!["c=d".to_string(), "=e=".to_string()]
+        )
+    );
+    assert_eq!(
+        parse_line("empty==", &ParseOptions::default(), 3).unwrap(),
+        ("empty".to_string(), "=".to_string(), vec![])
+    );
+}
+
+#[test]
+fn test_parse_line_str_delimiter() {
+    let opts = ParseOptions::with_delimiter("::");
+    assert_eq!(
+        parse_line("path = a,b :: c:d ::e", &opts, 1).unwrap(),
+        (
+            "path".to_string(),
+            "a,b".to_string(),
+            vec!["c:d".to_string(), "e".to_string()]
+        )
+    );
+}
+
+#[test]
+fn test_collapse_delimiters() {
+    let line = "list = a,,  ,b, ";
+    assert_eq!(
+        parse_line(line, &ParseOptions::default(), 1).unwrap().2,
+        vec!["", "", "b", ""]
+    );
+
+    let opts = ParseOptions {
+        collapse_delimiters: true,
+        ..ParseOptions::default()
+    };
+    assert_eq!(
+        parse_line(line, &opts, 1).unwrap(),
+        ("list".to_string(), "a".to_string(), vec!["b".to_string()])
+    );
+    assert_eq!(
+        parse_line("list = a,,", &opts, 2).unwrap().2,
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn test_normalize_attributes() {
+    let line = "mount = /Media/USB, REMOVABLE, Ro";
+    let opts = ParseOptions {
+        normalize_attributes: Some(Case::Lower),
+        ..ParseOptions::default()
+    };
+    assert_eq!(
+        parse_line(line, &opts, 1).unwrap(),
+        (
+            "mount".to_string(),
+            "/Media/USB".to_string(),
+            vec!["removable".to_string(), "ro".to_string()]
+        )
+    );
+
+    let opts = ParseOptions {
+        normalize_attributes: Some(Case::Upper),
+        ..ParseOptions::default()
+    };
+    assert_eq!(
+        parse_line(line, &opts, 1).unwrap().2,
+        vec!["REMOVABLE", "RO"]
+    );
+}
+
+#[test]
+fn test_whitespace_columns() {
+    use crate::Delimiter;
+
+    let opts = ParseOptions {
+        attr_delimiter: Delimiter::Whitespace,
+        whitespace_separator: true,
+        ..ParseOptions::default()
+    };
+    assert_eq!(
+        parse_line("/dev/sda1   /boot  ext4\tdefaults,noatime 0 2", &opts, 1).unwrap(),
+        (
+            "/dev/sda1".to_string(),
+            "/boot".to_string(),
+            vec![
+                "ext4".to_string(),
+                "defaults,noatime".to_string(),
+                "0".to_string(),
+                "2".to_string()
+            ]
+        )
+    );
+
+    let opts = ParseOptions::with_delimiter(Delimiter::Whitespace);
+    assert_eq!(
+        parse_line("path = /usr/bin  /bin", &opts, 2).unwrap(),
+        (
+            "path".to_string(),
+            "/usr/bin".to_string(),
+            vec!["/bin".to_string()]
+        )
+    );
+}
+
+#[test]
+fn test_whitespace_separator() {
+    let opts = ParseOptions {
+        whitespace_separator: true,
+        ..ParseOptions::default()
+    };
+    assert_eq!(
+        parse_line("listen   0.0.0.0, 8080", &opts, 1).unwrap(),
+        (
+            "listen".to_string(),
+            "0.0.0.0".to_string(),
+            vec!["8080".to_string()]
+        )
+    );
+    // An '=' still takes precedence
+    assert_eq!(
+        parse_line("greeting = hello world", &opts, 2).unwrap().1,
+        "hello world"
+    );
+    assert_eq!(parse_line("listen\t::1", &opts, 3).unwrap().1, "::1");
+    assert_eq!(
+        parse_line("DelayOff", &opts, 4).unwrap(),
+        ("DelayOff".to_string(), "".to_string(), vec![])
+    );
+}
+
+#[test]
+fn test_quoted_keys() {
+    let opts = ParseOptions {
+        quoted_keys: true,
+        reject_invalid_options: true,
+        ..ParseOptions::default()
+    };
+    let data =
+        "\"key with spaces\" = value, a\n  \"a=b\"=c\n\"say \\\"hi\\\"\"\n\"\" = empty\nplain = 1";
+    let config_vec = parse_str_with(data, &opts).unwrap();
+    let names: Vec<&str> = config_vec.iter().map(|o| o.option.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["key with spaces", "a=b", "say \"hi\"", "", "plain"]
+    );
+    assert_eq!(config_vec[0].value.primary, "value");
+    assert_eq!(config_vec[0].value.attributes, vec!["a"]);
+    assert_eq!(config_vec[1].value.primary, "c");
+    assert_eq!(config_vec[2].value.primary, "");
+
+    // Without a separator after the quotes (or the closing quote), the
+    // line is parsed as usual
+    assert!(parse_str_with("\"a b\" c = 1", &opts).is_err());
+    assert!(parse_str_with("\"a b = 1", &opts).is_err());
+    assert_eq!(
+        parse_str_with("\"ab\"x = 1", &opts).unwrap()[0].option,
+        "\"ab\"x"
+    );
+
+    let opts = ParseOptions {
+        quoted_keys: true,
+        whitespace_separator: true,
+        ..ParseOptions::default()
+    };
+    let config_vec = parse_str_with("\"max users\" 30", &opts).unwrap();
+    assert_eq!(config_vec[0].option, "max users");
+    assert_eq!(config_vec[0].value.primary, "30");
+
+    // By default the quotes are part of the name
+    let config_vec = parse_str("\"key with spaces\" = value", ',').unwrap();
+    assert_eq!(config_vec[0].option, "InvalidOption_on_Line1");
+}
+
+#[test]
+fn test_trim_keys() {
+    let opts = ParseOptions {
+        trim_keys: false,
+        reject_invalid_options: true,
+        ..ParseOptions::default()
+    };
+    let config_vec = parse_str_with("root = 1\n  child =2\n\tleaf\t\n", &opts).unwrap();
+    let names: Vec<&str> = config_vec.iter().map(|o| o.option.as_str()).collect();
+    assert_eq!(names, vec!["root ", "  child ", "\tleaf\t"]);
+    assert_eq!(config_vec[1].value.primary, "2");
+
+    // Whitespace inside the name is still invalid
+    let err = parse_str_with("  max users = 3", &opts).unwrap_err();
+    assert!(matches!(
+        err,
+        ParseError::WhitespaceInOptionName {
+            line: 1,
+            col: 6,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_option_name_chars() {
+    let opts = ParseOptions {
+        option_name_chars: Some(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.'),
+        ..ParseOptions::default()
+    };
+    assert_eq!(
+        parse_line("log.max_size = 30", &opts, 1).unwrap().0,
+        "log.max_size"
+    );
+    // Comments and blank lines aren't checked
+    assert!(parse_line("# log-max = 30", &opts, 2).is_ok());
+    assert!(parse_line("", &opts, 3).is_ok());
+
+    assert!(matches!(
+        parse_line("log-max = 30", &opts, 4),
+        Err(ParseError::InvalidOptionName { option, line: 4, col: 4 }) if option == "log-max"
+    ));
+    assert!(matches!(
+        parse_line("Hello World = bad option", &opts, 8),
+        Err(ParseError::InvalidOptionName {
+            line: 8,
+            col: 6,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_raw_invalid_lines() {
+    let opts = ParseOptions {
+        raw_invalid_lines: true,
+        folded_values: true,
+        ..ParseOptions::default()
+    };
+    // The lines after a raw line aren't folded into it
+    let data = "a = 1\nmatch host * {  \r\n    b\n}\nc = 2";
+    let entries = parse_str_entries(data, &opts).unwrap();
+    assert_eq!(entries.len(), 5);
+    assert_eq!(entries[1], Entry::Raw("match host * {  ".to_string()));
+    assert!(matches!(&entries[2], Entry::Option(o) if o.option == "b"));
+    assert_eq!(
+        entries[3],
+        Entry::Option(OptionProperties::new("}".into(), "".into(), vec![]))
+    );
+
+    let config_vec = crate::parse_str_with(data, &opts).unwrap();
+    let names: Vec<&str> = config_vec.iter().map(|o| o.option.as_str()).collect();
+    assert_eq!(names, vec!["a", "b", "}", "c"]);
+
+    // An option named like the placeholder of an invalid line is kept
+    let entries = parse_str_entries(
+        "InvalidOption_on_Line1
+bad name",
+        &opts,
+    )
+    .unwrap();
+    assert!(matches!(&entries[0], Entry::Option(o) if !o.is_invalid()));
+    assert_eq!(entries[1], Entry::Raw("bad name".to_string()));
+
+    // Rejecting invalid options takes precedence
+    let opts = ParseOptions {
+        raw_invalid_lines: true,
+        reject_invalid_options: true,
+        ..ParseOptions::default()
+    };
+    assert!(parse_str_entries(data, &opts).is_err());
+}
+
+#[test]
+fn test_reject_invalid_options() {
+    let opts = ParseOptions {
+        reject_invalid_options: true,
+        ..ParseOptions::default()
+    };
+    let err = parse_line("   max users = 30", &opts, 12).unwrap_err();
+    assert!(matches!(
+        &err,
+        ParseError::WhitespaceInOptionName { option, line: 12, col: 7 } if option == "max users"
+    ));
+    assert_eq!(
+        err.to_string(),
+        "line 12, col 7: whitespace in option name 'max users'"
+    );
+    assert_eq!((err.line(), err.col()), (Some(12), Some(7)));
+    assert!(parse_line("max_users = 30", &opts, 1).is_ok());
+}
+
+#[test]
+fn test_required_prefix() {
+    let mut opts = ParseOptions {
+        required_prefix: Some("app."),
+        ..ParseOptions::default()
+    };
+    let data = "# db.port = 1\n\napp.port = 80, x\n[web]\n  app.host = a";
+    let config_vec = crate::parse_str_with(data, &opts).unwrap();
+    let names: Vec<&str> = config_vec.iter().map(|o| o.option.as_str()).collect();
+    assert_eq!(names, vec!["app.port", "app.host"]);
+
+    let err = crate::parse_str_with("app.port = 80\ndb.port = 5432", &opts).unwrap_err();
+    assert!(matches!(
+        &err,
+        ParseError::MissingPrefix { option, line: 2, .. } if option == "db.port"
+    ));
+    assert_eq!(
+        err.to_string(),
+        "line 2: option 'db.port' doesn't start with 'app.'"
+    );
+    opts.quoted_keys = true;
+    assert_eq!(
+        parse_line("\"app.x y\" = 1", &opts, 1).unwrap().0,
+        "app.x y"
+    );
+
+    opts.strip_required_prefix = true;
+    opts.schema.insert("greeting".to_string(), Kind::Scalar);
+    let config_vec = crate::parse_str_with(data, &opts).unwrap();
+    let names: Vec<&str> = config_vec.iter().map(|o| o.option.as_str()).collect();
+    assert_eq!(names, vec!["port", "host"]);
+    assert_eq!(
+        parse_line("app.greeting = Hello, World", &opts, 1)
+            .unwrap()
+            .1,
+        "Hello, World"
+    );
+}
+
+#[test]
+fn test_include_directive() {
+    let opts = ParseOptions {
+        includes: true,
+        ..ParseOptions::default()
+    };
+    let entries = parse_str_entries(
+        "include a.conf\ninclude = x\n include_env  APP_CONF ",
+        &opts,
+    )
+    .unwrap();
+    assert_eq!(
+        entries[0],
+        Entry::Include(Include {
+            kind: IncludeKind::File,
+            target: "a.conf".to_string()
+        })
+    );
+    assert!(matches!(&entries[1], Entry::Option(o) if o.option == "include"));
+    assert_eq!(
+        entries[2],
+        Entry::Include(Include {
+            kind: IncludeKind::Env,
+            target: "APP_CONF".to_string()
+        })
+    );
+    assert_eq!(
+        parse_str_with("include_if_exists a.conf", &opts).unwrap(),
+        vec![]
+    );
+
+    // Not a directive without the option
+    let config_vec = parse_str("include a.conf", ',').unwrap();
+    assert_eq!(config_vec[0].option, "InvalidOption_on_Line1");
+}
+
+#[test]
+fn test_comment_requires_space() {
+    let opts = ParseOptions {
+        comment_requires_space: true,
+        ..ParseOptions::default()
+    };
+    let data =
+        "color=#fff\nport = 80 # the default\nfont = mono,\tbold\t# note\nflag #x\nc = a#b #c";
+    let config_vec = parse_str_with(data, &opts).unwrap();
+    assert_eq!(config_vec[0].value.primary, "#fff");
+    assert_eq!(config_vec[1].value.primary, "80");
+    assert_eq!(config_vec[2].value.attributes, vec!["bold"]);
+    assert_eq!(config_vec[3].option, "flag");
+    assert_eq!(config_vec[4].value.primary, "a#b");
+
+    // Escapes
+    let data = "message = say \\#hashtag # note\npath = C:\\dir\\\\# note\nraw = a\\b";
+    let config_vec = parse_str_with(data, &opts).unwrap();
+    assert_eq!(config_vec[0].value.primary, "say #hashtag");
+    assert_eq!(config_vec[1].value.primary, "C:\\dir\\");
+    assert_eq!(config_vec[2].value.primary, "a\\b");
+
+    // Without the option, '#' after the start of the line is kept
+    let config_vec = parse_str("port = 80 # the default\nm = \\#x", ',').unwrap();
+    assert_eq!(config_vec[1].value.primary, "\\#x");
+    assert_eq!(config_vec[0].value.primary, "80 # the default");
+}
+
+#[test]
+fn test_separator() {
+    let opts = ParseOptions {
+        separator: "=>",
+        ..ParseOptions::default()
+    };
+    let config_vec = parse_str_with("url => http://a/?x=1, y\nDelayOff\na=b", &opts).unwrap();
+    assert_eq!(config_vec[0].option, "url");
+    assert_eq!(config_vec[0].value.primary, "http://a/?x=1");
+    assert_eq!(config_vec[0].value.attributes, vec!["y"]);
+    assert_eq!(config_vec[1].option, "DelayOff");
+    assert_eq!(config_vec[2].option, "a=b");
+
+    let opts = ParseOptions {
+        separator: ":=",
+        whitespace_separator: true,
+        ..ParseOptions::default()
+    };
+    let config_vec = parse_str_with("port := 80\nlisten 0.0.0.0", &opts).unwrap();
+    assert_eq!(config_vec[0].value.primary, "80");
+    assert_eq!(config_vec[1].value.primary, "0.0.0.0");
+}
+
+#[test]
+fn test_block_comments() {
+    let opts = ParseOptions {
+        block_comments: Some(("/*", "*/")),
+        ..ParseOptions::default()
+    };
+    let data = "a = 1 /* one */\n/* a comment\n  b = 2\n*/ c = 3\nd = /* x */ 4 /* y\n */";
+    let entries = parse_str_entries(data, &opts).unwrap();
+    assert_eq!(
+        entries[1],
+        Entry::Comment(Comment {
+            text: "a comment".to_string(),
+            raw: "/* a comment".to_string(),
+            level: 0
+        })
+    );
+    assert!(matches!(&entries[2], Entry::Comment(c) if c.text == "b = 2"));
+    let config_vec = parse_str_with(data, &opts).unwrap();
+    let values: Vec<(&str, &str)> = config_vec
+        .iter()
+        .map(|o| (o.option.as_str(), o.value.primary.as_str()))
+        .collect();
+    assert_eq!(values, vec![("a", "1"), ("c", "3"), ("d", "4")]);
+
+    let err = parse_str_with("a = 1\nb = /x/* 2\nc = 3", &opts).unwrap_err();
+    assert!(matches!(
+        err,
+        ParseError::UnterminatedBlockComment { line: 2 }
+    ));
+    assert_eq!(err.to_string(), "line 2: unterminated block comment");
+
+    let err = parse_str_with("a = 1\n/* x\n/* y */ */", &opts).unwrap_err();
+    assert!(matches!(err, ParseError::NestedBlockComment { line: 3 }));
+
+    // Without the option, the markers are part of the value
+    let config_vec = parse_str("a = 1 /* one */", ',').unwrap();
+    assert_eq!(config_vec[0].value.primary, "1 /* one */");
+}
+
+#[test]
+fn test_schema() {
+    let schema = [("motd", Kind::Scalar), ("colors", Kind::List)]
+        .iter()
+        .map(|(name, kind)| (name.to_string(), *kind))
+        .collect();
+    let opts = ParseOptions::with_schema(schema);
+    let config_vec =
+        parse_str_with("motd = Hi, all , \ncolors = red, blue\nother = a, b", &opts).unwrap();
+    assert_eq!(config_vec[0].value.primary, "Hi, all ,");
+    assert!(config_vec[0].value.attributes.is_empty());
+    assert_eq!(config_vec[1].value.attributes, vec!["blue"]);
+    assert_eq!(config_vec[2].value.attributes, vec!["b"]);
+}
+
+#[test]
+fn test_max_attributes_per_option() {
+    let opts = ParseOptions {
+        max_attributes_per_option: Some(2),
+        collapse_delimiters: true,
+        ..ParseOptions::default()
+    };
+    let config_vec = parse_str_with("a = x, 1, 2\nb = x, 1,,, 2,\nc = x", &opts).unwrap();
+    assert_eq!(config_vec[1].value.attributes, vec!["1", "2"]);
+    let data = format!("a = 1\nb = x{}", ", y".repeat(100_000));
+    let err = parse_str_with(&data, &opts).unwrap_err();
+    assert!(matches!(
+        err,
+        ParseError::TooManyAttributes {
+            line: 2,
+            max: 2,
+            ..
+        }
+    ));
+    assert_eq!(err.to_string(), "line 2: more than 2 attributes for 'b'");
+
+    let opts = ParseOptions {
+        max_attributes_per_option: Some(0),
+        ..ParseOptions::default()
+    };
+    assert!(parse_str_with("a = 1", &opts).is_ok());
+    assert!(parse_str_with("a = 1,", &opts).is_err());
+}
+
+#[test]
+fn test_schema_items() {
+    let schema = [("servers", Kind::Items)]
+        .iter()
+        .map(|(name, kind)| (name.to_string(), *kind))
+        .collect();
+    let mut opts = ParseOptions::with_schema(schema);
+    opts.normalize_attributes = Some(Case::Lower);
+    let config_vec = parse_str_with(
+        "servers = A:1, b:2 , c:3\nservers = a:1\nservers\nother = X, Y",
+        &opts,
+    )
+    .unwrap();
+    assert_eq!(config_vec[0].value.primary, "");
+    assert_eq!(config_vec[0].value.attributes, vec!["a:1", "b:2", "c:3"]);
+    assert_eq!(config_vec[1].value.attributes, vec!["a:1"]);
+    assert!(config_vec[2].value.attributes.is_empty());
+    assert_eq!(config_vec[3].value.primary, "X");
+    assert_eq!(config_vec[3].value.attributes, vec!["y"]);
+
+    opts.bracket_values = true;
+    let config_vec = parse_str_with("servers = [a, b], c", &opts).unwrap();
+    assert_eq!(config_vec[0].value.primary, "");
+    assert_eq!(config_vec[0].value.attributes, vec!["[a, b]", "c"]);
+}
+
+#[test]
+fn test_schema_numbers() {
+    let schema = [("weights", Kind::Numbers)]
+        .iter()
+        .map(|(name, kind)| (name.to_string(), *kind))
+        .collect();
+    let opts = ParseOptions::with_schema(schema);
+    let config_vec = parse_str_with("weights = 1, -2.5 ,3e2\nweights\nother = x", &opts).unwrap();
+    assert_eq!(config_vec[0].value.attributes, vec!["-2.5", "3e2"]);
+    assert_eq!(config_vec.len(), 3);
+
+    let err = parse_str_with("# w\nweights = 1, two, 3", &opts).unwrap_err();
+    assert!(matches!(
+        err,
+        ParseError::NotANumber { ref value, index: 1, line: 2, .. } if value == "two"
+    ));
+    for data in ["weights = x", "weights = 1,, 2", "weights = 1, inf"].iter() {
+        assert!(parse_str_with(data, &opts).is_err(), "{}", data);
+    }
+}
+
+#[test]
+fn test_folded_values() {
+    let opts = ParseOptions {
+        folded_values: true,
+        block_values: true,
+        ..ParseOptions::default()
+    };
+    let data =
+        "  description = A long\n    description that\n   continues\n  next = 1\n  not folded\n\
+                motd = Hi,\n  all\n# comment\n  x\nscript = <<END\n  echo\nEND";
+    let config_vec = parse_str_with(data, &opts).unwrap();
+    assert_eq!(
+        config_vec[0].value.primary,
+        "A long description that continues"
+    );
+    assert_eq!(config_vec[1].value.primary, "1");
+    assert_eq!(config_vec[2].option, "InvalidOption_on_Line5");
+    assert_eq!(config_vec[3].value.attributes, vec!["all"]);
+    assert_eq!(config_vec[4].option, "x");
+    assert_eq!(config_vec[5].value.primary, "  echo");
+
+    // Without the option, the lines are options of their own
+    let config_vec = parse_str("a = b\n  c", ',').unwrap();
+    assert_eq!(config_vec.len(), 2);
+}
+
+#[test]
+fn test_value_transform() {
+    use crate::Callback;
+    use alloc::boxed::Box;
+
+    let unquote = |v: &str| v.trim_matches('"').to_string();
+    let opts = ParseOptions {
+        value_transform: Some(Callback::new(Box::new(unquote))),
+        ..ParseOptions::default()
+    };
+    let config_vec = parse_str_with("name = \"Bob\", \"x\"\nDelayOff", &opts).unwrap();
+    assert_eq!(config_vec[0].value.primary, "Bob");
+    assert_eq!(config_vec[0].value.attributes, vec!["\"x\""]);
+    assert_eq!(config_vec[1].value.primary, "");
+
+    let opts = ParseOptions {
+        value_transform: Some(Callback::new(Box::new(unquote))),
+        transform_attributes: true,
+        dedup_attributes: true,
+        ..ParseOptions::default()
+    };
+    let config_vec = parse_str_with("name = \"Bob\", \"x\", x", &opts).unwrap();
+    assert_eq!(config_vec[0].value.attributes, vec!["x"]);
+}
+
+#[test]
+fn test_dedup_attributes_option() {
+    let opts = ParseOptions {
+        dedup_attributes: true,
+        normalize_attributes: Some(Case::Lower),
+        ..ParseOptions::default()
+    };
+    let config_vec = parse_str_with("tags = x, red, Red, green, red", &opts).unwrap();
+    assert_eq!(config_vec[0].value.attributes, vec!["red", "green"]);
+    let config_vec = parse_str("tags = x, red, red", ',').unwrap();
+    assert_eq!(config_vec[0].value.attributes, vec!["red", "red"]);
+}
+
+#[test]
+fn test_reject_mixed_indent() {
+    let opts = ParseOptions {
+        reject_mixed_indent: true,
+        ..ParseOptions::default()
+    };
+    assert!(parse_str_with("a = 1\n\tb = 2\n    c = 3\n \t \n", &opts).is_ok());
+    let err = parse_str_with("a = 1\n\t # comment\n", &opts).unwrap_err();
+    assert!(matches!(err, ParseError::MixedIndent { line: 2 }));
+    assert_eq!(err.to_string(), "line 2: indentation mixes tabs and spaces");
+    assert!(parse_str_with(" \tb = 2 \t ", &opts).is_err());
+    assert!(parse_str(" \tb = 2", ',').is_ok());
+}
+
+#[test]
+fn test_bracket_values() {
+    use crate::Delimiter;
+
+    let opts = ParseOptions {
+        bracket_values: true,
+        ..ParseOptions::default()
+    };
+    let data = "matrix = [1=2, 3=4]\nmap = {a: [1, 2], b: 3}, x, [y, z]\nplain = a, b";
+    let config_vec = parse_str_with(data, &opts).unwrap();
+    assert_eq!(config_vec[0].value.primary, "[1=2, 3=4]");
+    assert!(config_vec[0].value.attributes.is_empty());
+    assert_eq!(config_vec[1].value.primary, "{a: [1, 2], b: 3}");
+    assert_eq!(config_vec[1].value.attributes, vec!["x", "[y, z]"]);
+    assert_eq!(config_vec[2].value.attributes, vec!["b"]);
+
+    let opts = ParseOptions {
+        bracket_values: true,
+        attr_delimiter: Delimiter::Whitespace,
+        ..ParseOptions::default()
+    };
+    let config_vec = parse_str_with("m = [1  2] x", &opts).unwrap();
+    assert_eq!(config_vec[0].value.primary, "[1  2]");
+    assert_eq!(config_vec[0].value.attributes, vec!["x"]);
+
+    for bad in ["a = 1\nm = [1, 2", "m = 1]", "m = [1, 2}"] {
+        let err = parse_str_with(
+            bad,
+            &ParseOptions {
+                bracket_values: true,
+                ..ParseOptions::default()
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, ParseError::UnbalancedBrackets { .. }));
+    }
+    assert_eq!(
+        parse_str_with("a = 1\nm = [", &opts)
+            .unwrap_err()
+            .to_string(),
+        "line 2: unbalanced brackets in the value"
+    );
+}
+
+#[test]
+fn test_quoted_values() {
+    let opts = ParseOptions {
+        quoted_values: true,
+        ..ParseOptions::default()
+    };
+    let data = r#"font = "Noto, Bold", " x ", "say \"hi\"", c:\dir
+plain = a, "b, c
+whole = "a, b"
+half = "a" b, "c"d"#;
+    let config_vec = parse_str_with(data, &opts).unwrap();
+    assert_eq!(config_vec[0].value.primary, "Noto, Bold");
+    assert_eq!(
+        config_vec[0].value.attributes,
+        vec![" x ", r#"say "hi""#, r"c:\dir"]
+    );
+    // An unterminated quote runs to the end of the value, and is kept
+    assert_eq!(config_vec[1].value.attributes, vec![r#""b, c"#]);
+    assert_eq!(config_vec[2].value.primary, "a, b");
+    assert!(config_vec[2].value.attributes.is_empty());
+    // Values only partly in quotes are kept as they are
+    assert_eq!(config_vec[3].value.primary, r#""a" b"#);
+    assert_eq!(config_vec[3].value.attributes, vec![r#""c"d"#]);
+
+    // Brackets inside quotes don't count
+    let opts = ParseOptions {
+        bracket_values: true,
+        ..opts
+    };
+    let config_vec = parse_str_with(r#"m = "[1", [2, 3], "}", x"#, &opts).unwrap();
+    assert_eq!(config_vec[0].value.primary, "[1");
+    assert_eq!(config_vec[0].value.attributes, vec!["[2, 3]", "}", "x"]);
+    assert!(parse_str_with(r#"m = "[1", [2"#, &opts).is_err());
+    assert!(parse_str_with(r#"m = "[1"#, &opts).is_ok());
+
+    // Without quoted_values, the quotes are part of the value
+    let config_vec = parse_str(r#"a = "x, y""#, ',').unwrap();
+    assert_eq!(config_vec[0].value.primary, r#""x"#);
+}
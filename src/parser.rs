@@ -0,0 +1,80 @@
+use std::io::{self, BufRead};
+use std::iter::Map;
+
+use crate::parse::LineParser;
+use crate::{Entry, OptionProperties, ParseError, ParseOptions};
+
+type Lines<R> = Map<io::Lines<R>, fn(io::Result<String>) -> Result<String, ParseError>>;
+
+/// A pull parser reading a configuration from a buffered reader. Nothing is
+/// read until an option is asked for, and each call reads only as many lines
+/// as the next option needs, so parsing can be interleaved with other work
+/// or stopped at any point.
+///
+/// # Examples
+///
+/// ```
+/// use configster::{ParseOptions, Parser};
+///
+/// let data = "# comment\nversion = 2\nmax_users = 30\n";
+/// let opts = ParseOptions::default();
+/// let mut parser = Parser::new(data.as_bytes(), &opts);
+/// let version = parser.next_option().unwrap().unwrap();
+/// assert_eq!(version.value.primary, "2");
+/// assert_eq!(parser.line(), 2);
+/// ```
+pub struct Parser<'o, 'a, R: BufRead> {
+    lines: LineParser<'o, 'a, Lines<R>>,
+}
+
+impl<'o, 'a, R: BufRead> Parser<'o, 'a, R> {
+    pub fn new(reader: R, opts: &'o ParseOptions<'a>) -> Self {
+        let lines: Lines<R> = reader.lines().map(|l| l.map_err(ParseError::from));
+        Self {
+            lines: LineParser::new(lines, opts),
+        }
+    }
+
+    /// Reads up to the next option and returns it, or `None` at the end of
+    /// the input.
+    pub fn next_option(&mut self) -> Option<io::Result<OptionProperties>> {
+        loop {
+            match self.lines.next()? {
+                Ok(Entry::Option(opt_props)) => return Some(Ok(opt_props)),
+                Ok(_) => {}
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+    }
+
+    /// Reads the next line (or block value) and returns it as an
+    /// [Entry](enum.Entry.html), or `None` at the end of the input.
+    pub fn next_entry(&mut self) -> Option<io::Result<Entry>> {
+        Some(self.lines.next()?.map_err(io::Error::from))
+    }
+
+    /// Returns the line number of the option or entry returned last.
+    pub fn line(&self) -> usize {
+        self.lines.line()
+    }
+}
+
+#[test]
+fn test_parser() {
+    let data = "a = 1\n\n[s]\nscript = <<END\necho\nEND\nb = 2\nc = <<END\n";
+    let opts = ParseOptions {
+        block_values: true,
+        ..ParseOptions::default()
+    };
+    let mut parser = Parser::new(data.as_bytes(), &opts);
+    assert_eq!(parser.next_option().unwrap().unwrap().option, "a");
+    assert!(matches!(parser.next_entry(), Some(Ok(Entry::Blank))));
+    let script = parser.next_option().unwrap().unwrap();
+    assert_eq!(script.value.primary, "echo");
+    assert_eq!(script.section.as_deref(), Some("s"));
+    assert_eq!(parser.line(), 4);
+    assert_eq!(parser.next_option().unwrap().unwrap().option, "b");
+    let err = parser.next_option().unwrap().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(parser.next_option().is_none());
+}
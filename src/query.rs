@@ -0,0 +1,283 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+
+use crate::OptionProperties;
+
+/// Returns the first option named `option` within `section` (`None` for the
+/// options that come before any section header).
+///
+/// # Examples
+///
+/// ```
+/// let config_vec = configster::parse_str("[logging]\nlevel = 2\n[network]\nlevel = 3", ',').unwrap();
+/// let opt = configster::get_in_section(&config_vec, Some("network"), "level").unwrap();
+/// assert_eq!(opt.value.primary, "3");
+/// assert!(configster::get_in_section(&config_vec, None, "level").is_none());
+/// ```
+pub fn get_in_section<'a>(
+    vec: &'a [OptionProperties],
+    section: Option<&str>,
+    option: &str,
+) -> Option<&'a OptionProperties> {
+    vec.iter()
+        .find(|o| o.section.as_deref() == section && o.option == option)
+}
+
+/// Returns an iterator over the options named `name`, in the order they appear
+/// in the file. Unlike collecting the matches, nothing is allocated.
+///
+/// # Examples
+///
+/// ```
+/// let config_vec = configster::parse_str("server = a\nport = 80\nserver = b", ',').unwrap();
+/// let servers: Vec<&str> = configster::options_named(&config_vec, "server")
+///     .map(|o| o.value.primary.as_str())
+///     .collect();
+/// assert_eq!(servers, vec!["a", "b"]);
+/// ```
+pub fn options_named<'a, 'n>(
+    vec: &'a [OptionProperties],
+    name: &'n str,
+) -> impl Iterator<Item = &'a OptionProperties> + 'n
+where
+    'a: 'n,
+{
+    vec.iter().filter(move |o| o.option == name)
+}
+
+/// Returns the `n`th (counting from 0) option named `option`, e.g. the second
+/// of several fallback servers.
+///
+/// # Examples
+///
+/// ```
+/// let config_vec = configster::parse_str("server = a\nport = 80\nserver = b", ',').unwrap();
+/// assert_eq!(configster::get_nth(&config_vec, "server", 1).unwrap().value.primary, "b");
+/// assert!(configster::get_nth(&config_vec, "server", 2).is_none());
+/// ```
+pub fn get_nth<'a>(
+    vec: &'a [OptionProperties],
+    option: &str,
+    n: usize,
+) -> Option<&'a OptionProperties> {
+    options_named(vec, option).nth(n)
+}
+
+/// Returns the options whose names start with `prefix` (e.g. every
+/// "plugin.foo.*" option for the prefix "plugin.foo."), in the order they
+/// appear in the file.
+///
+/// # Examples
+///
+/// ```
+/// let data = "plugin.foo.enabled = true\nplugin.bar.path = /b\nplugin.foo.path = /f";
+/// let config_vec = configster::parse_str(data, ',').unwrap();
+/// let foo = configster::options_with_prefix(&config_vec, "plugin.foo.");
+/// assert_eq!(foo.len(), 2);
+/// assert_eq!(foo[1].value.primary, "/f");
+/// ```
+pub fn options_with_prefix<'a>(
+    vec: &'a [OptionProperties],
+    prefix: &str,
+) -> Vec<&'a OptionProperties> {
+    vec.iter()
+        .filter(|o| o.option.starts_with(prefix))
+        .collect()
+}
+
+/// Returns the names of the flag options (those without a value) in `section`,
+/// e.g. the enabled features listed under a "[features]" header. The invalid
+/// lines, which are parsed as options without a value, aren't flags.
+///
+/// # Examples
+///
+/// ```
+/// let config_vec = configster::parse_str("[features]\ndark_mode\nbeta = off\nsync", ',').unwrap();
+/// let flags = configster::section_flags(&config_vec, "features");
+/// assert!(flags.contains("dark_mode") && flags.contains("sync"));
+/// assert_eq!(flags.len(), 2);
+/// ```
+#[cfg(feature = "std")]
+pub fn section_flags(vec: &[OptionProperties], section: &str) -> HashSet<String> {
+    flags(vec, section).map(String::from).collect()
+}
+
+/// The names of the flag options in `section`, in order, for
+/// [section_flags](fn.section_flags.html) and `Config::flags_in_section`.
+pub(crate) fn flags<'a: 's, 's>(
+    vec: &'a [OptionProperties],
+    section: &'s str,
+) -> impl Iterator<Item = &'a str> + 's {
+    vec.iter()
+        .filter(move |o| o.section.as_deref() == Some(section))
+        .filter(|o| o.value.primary.is_empty() && o.value.attributes.is_empty())
+        .filter(|o| !o.is_invalid())
+        .map(|o| o.option.as_str())
+}
+
+/// Returns the number of times each option name occurs, in any section, e.g.
+/// to find the options that are set more than once.
+///
+/// # Examples
+///
+/// ```
+/// let config_vec = configster::parse_str("port = 80\nhost = a\n[db]\nport = 5432\nport = 6543", ',').unwrap();
+/// let counts = configster::option_counts(&config_vec);
+/// assert_eq!(counts["port"], 3);
+/// assert_eq!(counts["host"], 1);
+/// let repeated: Vec<&String> = counts.iter().filter(|(_, &n)| n > 1).map(|(o, _)| o).collect();
+/// assert_eq!(repeated, vec!["port"]);
+/// ```
+#[cfg(feature = "std")]
+pub fn option_counts(vec: &[OptionProperties]) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for opt_props in vec {
+        match counts.get_mut(opt_props.option.as_str()) {
+            Some(count) => *count += 1,
+            None => {
+                counts.insert(opt_props.option.clone(), 1);
+            }
+        }
+    }
+    counts
+}
+
+/// Checks that every option in `names` is present. On failure, returns the
+/// names of the missing options, in the order they were given.
+///
+/// # Examples
+///
+/// ```
+/// let config_vec = configster::parse_str("max_users = 30", ',').unwrap();
+/// assert!(configster::require(&config_vec, &["max_users"]).is_ok());
+/// assert_eq!(
+///     configster::require(&config_vec, &["socket", "max_users", "user"]),
+///     Err(vec!["socket".to_string(), "user".to_string()])
+/// );
+/// ```
+pub fn require(vec: &[OptionProperties], names: &[&str]) -> Result<(), Vec<String>> {
+    let missing: Vec<String> = names
+        .iter()
+        .filter(|name| !vec.iter().any(|o| o.option == **name))
+        .map(|name| name.to_string())
+        .collect();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(missing)
+    }
+}
+
+#[test]
+fn test_get_in_section() {
+    let config_vec = crate::parse_str(
+        "level = 0\n[logging]\nlevel = 1\nlevel = 2\n[network]\nlevel = 3",
+        ',',
+    )
+    .unwrap();
+    let primary = |section, option| {
+        get_in_section(&config_vec, section, option).map(|o| o.value.primary.as_str())
+    };
+    assert_eq!(primary(None, "level"), Some("0"));
+    assert_eq!(primary(Some("logging"), "level"), Some("1"));
+    assert_eq!(primary(Some("network"), "level"), Some("3"));
+    assert_eq!(primary(Some("storage"), "level"), None);
+    assert_eq!(primary(Some("logging"), "size"), None);
+}
+
+#[test]
+fn test_require() {
+    let config_vec = crate::parse_str("max_users = 30\n[db]\nport = 5432\nDelayOff", ',').unwrap();
+    assert_eq!(require(&config_vec, &[]), Ok(()));
+    assert_eq!(
+        require(&config_vec, &["DelayOff", "port", "max_users"]),
+        Ok(())
+    );
+    assert_eq!(
+        require(&config_vec, &["user", "port", "host"]),
+        Err(vec!["user".to_string(), "host".to_string()])
+    );
+}
+
+#[test]
+fn test_options_named() {
+    let config_vec = crate::parse_str(
+        "server = a\n[backup]\nserver = b\nport = 80\nserver = c",
+        ',',
+    )
+    .unwrap();
+    let mut servers = options_named(&config_vec, "server");
+    assert_eq!(servers.next().unwrap().value.primary, "a");
+    assert_eq!(servers.next().unwrap().value.primary, "b");
+    assert_eq!(servers.next().unwrap().value.primary, "c");
+    assert!(servers.next().is_none());
+    assert_eq!(options_named(&config_vec, "host").count(), 0);
+}
+
+#[test]
+fn test_get_nth() {
+    let config_vec = crate::parse_str(
+        "server = a\n[backup]\nserver = b\nport = 80\nserver = c",
+        ',',
+    )
+    .unwrap();
+    assert_eq!(
+        get_nth(&config_vec, "server", 0).unwrap().value.primary,
+        "a"
+    );
+    assert_eq!(
+        get_nth(&config_vec, "server", 2).unwrap().value.primary,
+        "c"
+    );
+    assert!(get_nth(&config_vec, "server", 3).is_none());
+    assert!(get_nth(&config_vec, "host", 0).is_none());
+}
+
+#[test]
+fn test_options_with_prefix() {
+    let config_vec = crate::parse_str(
+        "plugin.foo.enabled = true\nplugin.foobar = x\nplugin.foo.path = /f\nfoo.plugin.x = 1",
+        ',',
+    )
+    .unwrap();
+    let names: Vec<&str> = options_with_prefix(&config_vec, "plugin.foo.")
+        .iter()
+        .map(|o| o.option.as_str())
+        .collect();
+    assert_eq!(names, vec!["plugin.foo.enabled", "plugin.foo.path"]);
+    assert_eq!(options_with_prefix(&config_vec, "").len(), 4);
+    assert!(options_with_prefix(&config_vec, "plugins.").is_empty());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_section_flags() {
+    let config_vec = crate::parse_str(
+        "top_flag\n[features]\ndark_mode\nbeta = off\n  sync  \nnot a flag\n[other]\nlegacy\n[features]\nsearch\nInvalidOption_on_Line1",
+        ',',
+    )
+    .unwrap();
+    let mut flags: Vec<String> = section_flags(&config_vec, "features").into_iter().collect();
+    flags.sort();
+    assert_eq!(
+        flags,
+        vec!["InvalidOption_on_Line1", "dark_mode", "search", "sync"]
+    );
+    assert!(section_flags(&config_vec, "missing").is_empty());
+    // The invalid line is in the options, but isn't a flag
+    assert_eq!(config_vec[4].option, "InvalidOption_on_Line6");
+    assert!(config_vec[4].is_invalid());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_option_counts() {
+    let config_vec = crate::parse_str("a = 1\nb\n[s]\na = 2, x\n[t]\na\nc = 3", ',').unwrap();
+    let counts = option_counts(&config_vec);
+    let mut counts: Vec<(&str, usize)> = counts.iter().map(|(o, &n)| (o.as_str(), n)).collect();
+    counts.sort();
+    assert_eq!(counts, vec![("a", 3), ("b", 1), ("c", 1)]);
+    assert!(option_counts(&[]).is_empty());
+}
@@ -0,0 +1,992 @@
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+use core::str::FromStr;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+
+use crate::parse::split_value;
+use crate::write::{write_option, write_quoted};
+use crate::{ParseError, ParseOptions, ParseValueError, DEFAULT_ATTR_DELIMIT_CHAR};
+
+/// The type for each Option; holds the information
+/// for each element of a line in a config file.
+///
+/// # Examples
+///
+/// A function argument:
+///
+/// ```text
+/// fn assign_properties(st_option_props: &configster::OptionProperties, homedir: &str) {
+///     let mut value = "$HOME/Documents";
+///     value = st_option_props.value.primary.replace("$HOME", &homedir);
+/// }
+/// ```
+///
+/// A return type:
+///
+/// ```text
+/// fn parse(
+///     opt_cfg: Option<String>,
+///     homedir: String,
+/// ) -> io::Result<Vec<configster::OptionProperties>> {
+///     // ...
+///     Ok(config_vec)
+/// }
+/// ```
+///
+/// Options are ordered by name, then primary value, then attributes (and
+/// finally section), so a vector of options can be sorted and deduplicated.
+/// They can also be hashed, e.g. to deduplicate the options of several files
+/// with a `HashSet`. Whether an option stands for an invalid line (see
+/// [is_invalid](#method.is_invalid)) isn't compared.
+#[derive(Clone, Debug)]
+pub struct OptionProperties {
+    pub option: String,
+    pub value: Value,
+    /// The name of the section ("[name]") the option appears under, or `None`
+    /// if it comes before any section header.
+    pub section: Option<String>,
+    pub(crate) invalid: bool,
+}
+
+/// The type holding the primary value and the attributes; this is a nested type
+/// within [OptionProperties](struct.OptionProperties.html).
+///
+/// Values are ordered by primary value (compared as strings, so "10" comes
+/// before "9"), then attributes, e.g. to sort a table of options by value:
+///
+/// ```
+/// let config_vec = configster::parse_str("a = apple\nb = pear, x\nc = pear", ',').unwrap();
+/// let max = config_vec.iter().max_by(|a, b| a.value.cmp(&b.value)).unwrap();
+/// assert_eq!(max.option, "b");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Value {
+    /// A string following the option and an '=' sign in a [configuration file](https://github.com/theimpossibleastronaut/configster/blob/trunk/README.md#config-file-format).
+    /// (e.g. "directory = /home/foo")
+    pub primary: String,
+    /// A list separated by a delimiter, which is specified as a parameter in
+    /// [parse_file](fn.parse_file.html).
+    pub attributes: Vec<String>,
+}
+
+impl Value {
+    /// The primary values [is_truthy](#method.is_truthy) accepts as true.
+    pub const TRUTHY: &'static [&'static str] = &["true", "yes", "on", "1", "enabled"];
+
+    /// Splits a value string (the part of an option line after the '=' sign)
+    /// into the primary value and the attributes, with `attr_delimit_char` as
+    /// the attribute delimiter, the way the parse functions do. Parsing with
+    /// [DEFAULT_ATTR_DELIMIT_CHAR](constant.DEFAULT_ATTR_DELIMIT_CHAR.html) is
+    /// also available as `Value::try_from(s)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use configster::Value;
+    ///
+    /// let value = Value::from_str_with("Blue; light ; shiny", ';').unwrap();
+    /// assert_eq!(value.primary, "Blue");
+    /// assert_eq!(value.attributes, vec!["light", "shiny"]);
+    /// ```
+    pub fn from_str_with(s: &str, attr_delimit_char: char) -> Result<Self, ParseError> {
+        let opts = ParseOptions::with_delimiter(attr_delimit_char);
+        let (primary, attributes) = split_value("", s.trim(), &opts, 1)?;
+        Ok(Self {
+            primary,
+            attributes,
+        })
+    }
+
+    /// Returns the primary value, or `default` if the primary is empty (e.g.
+    /// for an option with no value, such as "DelayOff").
+    #[inline]
+    pub fn primary_or<'a>(&'a self, default: &'a str) -> &'a str {
+        if self.primary.is_empty() {
+            default
+        } else {
+            &self.primary
+        }
+    }
+
+    /// Returns the primary value with the characters in `pat` trimmed from
+    /// both ends, e.g. to remove quotes or angle brackets around the value of
+    /// a particular option.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let config_vec = configster::parse_str("name = \"Bob\"\nmail = <bob@example.com>", ',').unwrap();
+    /// assert_eq!(config_vec[0].value.primary_trimmed_of(&['"']), "Bob");
+    /// assert_eq!(config_vec[1].value.primary_trimmed_of(&['<', '>']), "bob@example.com");
+    /// ```
+    pub fn primary_trimmed_of(&self, pat: &[char]) -> &str {
+        self.primary.trim_matches(pat)
+    }
+
+    /// Returns the primary value and the attributes, for an option whose
+    /// primary value is a type tag and whose attributes are the properties
+    /// of that type ("widget = button, label=OK, onclick=submit").
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let config_vec = configster::parse_str("widget = button, label=OK, onclick=submit", ',').unwrap();
+    /// match config_vec[0].value.tag_and_attrs() {
+    ///     ("button", props) => assert_eq!(props, ["label=OK", "onclick=submit"]),
+    ///     (tag, _) => panic!("unknown widget {}", tag),
+    /// }
+    /// ```
+    pub fn tag_and_attrs(&self) -> (&str, &[String]) {
+        (&self.primary, &self.attributes)
+    }
+
+    /// Returns true if `attr` is one of the attributes. Surrounding whitespace
+    /// is ignored.
+    pub fn has_attr(&self, attr: &str) -> bool {
+        let attr = attr.trim();
+        self.attributes.iter().any(|a| a.trim() == attr)
+    }
+
+    /// Like [has_attr](#method.has_attr), but ignores ASCII case.
+    pub fn has_attr_ignore_case(&self, attr: &str) -> bool {
+        let attr = attr.trim();
+        self.attributes
+            .iter()
+            .any(|a| a.trim().eq_ignore_ascii_case(attr))
+    }
+
+    /// Returns the primary value followed by the attributes, for options that
+    /// are really a single list (e.g. "colors = red, green, blue"). An empty
+    /// primary value is left out.
+    pub fn all_values(&self) -> Vec<&str> {
+        let mut values = Vec::with_capacity(self.attributes.len() + 1);
+        if !self.primary.is_empty() {
+            values.push(self.primary.as_str());
+        }
+        values.extend(self.attributes.iter().map(String::as_str));
+        values
+    }
+
+    /// Splits the primary value at each `sep` and trims the pieces, as is done
+    /// for the attributes (e.g. ["10", "20"] for "range = 10 - 20" split at
+    /// '-').
+    pub fn split_primary(&self, sep: char) -> Vec<&str> {
+        self.primary.split(sep).map(str::trim).collect()
+    }
+
+    /// Splits each attribute at the first `sep` into a key and a value, both
+    /// trimmed; an attribute without `sep` has no value. The attributes
+    /// themselves are split at
+    /// [ParseOptions::attr_delimiter](struct.ParseOptions.html#structfield.attr_delimiter),
+    /// so the two separators can be chosen independently.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use configster::ParseOptions;
+    ///
+    /// let config_vec = configster::parse_str("cache = redis, host=localhost, port=6379, tls", ',').unwrap();
+    /// assert_eq!(
+    ///     config_vec[0].value.attribute_pairs('='),
+    ///     vec![("host", Some("localhost")), ("port", Some("6379")), ("tls", None)]
+    /// );
+    ///
+    /// let opts = ParseOptions::with_delimiter(';');
+    /// let config_vec = configster::parse_str_with("cache = redis; host: localhost; port: 6379", &opts).unwrap();
+    /// assert_eq!(config_vec[0].value.attribute_pairs(':')[1], ("port", Some("6379")));
+    /// ```
+    pub fn attribute_pairs(&self, sep: char) -> Vec<(&str, Option<&str>)> {
+        self.attributes
+            .iter()
+            .map(|a| match a.split_once(sep) {
+                Some((key, value)) => (key.trim(), Some(value.trim())),
+                None => (a.trim(), None),
+            })
+            .collect()
+    }
+
+    /// Returns true if the primary value is one of [TRUTHY](#associatedconstant.TRUTHY)
+    /// (ignoring ASCII case), and false for anything else, including an empty
+    /// value. For lenient flag checks that never fail.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let config_vec = configster::parse_str("a = Yes\nb = off\nc = maybe", ',').unwrap();
+    /// assert!(config_vec[0].value.is_truthy());
+    /// assert!(!config_vec[1].value.is_truthy());
+    /// assert!(!config_vec[2].value.is_truthy());
+    /// ```
+    pub fn is_truthy(&self) -> bool {
+        self.is_truthy_in(Self::TRUTHY)
+    }
+
+    /// Like [is_truthy](#method.is_truthy), with the given set of true values.
+    pub fn is_truthy_in(&self, truthy: &[&str]) -> bool {
+        let primary = self.primary.trim();
+        truthy.iter().any(|t| t.eq_ignore_ascii_case(primary))
+    }
+
+    /// Returns the attributes, or `default` if there are none (e.g. for
+    /// "mode = fast", where no attributes means a default set applies).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let config_vec = configster::parse_str("mode = fast\nother = slow, safe", ',').unwrap();
+    /// assert_eq!(config_vec[0].value.attributes_or_default(&["default"]), vec!["default"]);
+    /// assert_eq!(config_vec[1].value.attributes_or_default(&["default"]), vec!["safe"]);
+    /// ```
+    pub fn attributes_or_default(&self, default: &[&str]) -> Vec<String> {
+        if self.attributes.is_empty() {
+            default.iter().map(|&a| a.into()).collect()
+        } else {
+            self.attributes.clone()
+        }
+    }
+
+    /// Removes repeated attributes, keeping the first occurrence of each
+    /// (e.g. "tags = x, red, red, green" keeps ["red", "green"]). This takes
+    /// O(n log n) time for n attributes.
+    pub fn dedup_attributes(&mut self) {
+        let mut seen = BTreeSet::new();
+        let keep: Vec<bool> = self
+            .attributes
+            .iter()
+            .map(|a| seen.insert(a.as_str()))
+            .collect();
+        let mut keep = keep.into_iter();
+        self.attributes.retain(|_| keep.next().unwrap_or(true));
+    }
+
+    /// Parses each attribute as a `T`. On failure, returns the index and the
+    /// text of the first attribute that doesn't parse.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let config_vec = configster::parse_str("ports = tcp, 80, 443\nbad = tcp, 80, http", ',').unwrap();
+    /// assert_eq!(config_vec[0].value.attributes_as::<u16>(), Ok(vec![80, 443]));
+    /// assert_eq!(config_vec[1].value.attributes_as::<u16>(), Err((1, "http".to_string())));
+    /// ```
+    pub fn attributes_as<T: FromStr>(&self) -> Result<Vec<T>, (usize, String)> {
+        self.attributes
+            .iter()
+            .enumerate()
+            .map(|(i, a)| a.parse().map_err(|_| (i, a.clone())))
+            .collect()
+    }
+
+    /// Parses the primary value as a duration: a whole number followed by one
+    /// of the units "ms", "s", "m" (minutes), "h" or "d" ("30s", "5 m"). A
+    /// number without a unit is in seconds; see
+    /// [as_duration_with_unit](#method.as_duration_with_unit).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// let config_vec = configster::parse_str("timeout = 30s\nretry = 5m\ndelay = 250ms\nbad = 5 weeks", ',').unwrap();
+    /// assert_eq!(config_vec[0].value.as_duration(), Ok(Duration::from_secs(30)));
+    /// assert_eq!(config_vec[1].value.as_duration(), Ok(Duration::from_secs(300)));
+    /// assert_eq!(config_vec[2].value.as_duration(), Ok(Duration::from_millis(250)));
+    /// assert_eq!(
+    ///     config_vec[3].value.as_duration().unwrap_err().to_string(),
+    ///     "unknown unit 'weeks' in '5 weeks'"
+    /// );
+    /// ```
+    pub fn as_duration(&self) -> Result<Duration, ParseValueError> {
+        self.as_duration_with_unit(Duration::from_secs(1))
+    }
+
+    /// Like [as_duration](#method.as_duration), with a number without a unit
+    /// counted in `unit`s (e.g. `Duration::from_millis(1)`).
+    pub fn as_duration_with_unit(&self, unit: Duration) -> Result<Duration, ParseValueError> {
+        let (n, suffix) = self.number_and_unit()?;
+        let unit = match suffix {
+            "" => unit,
+            "ms" => Duration::from_millis(1),
+            "s" => Duration::from_secs(1),
+            "m" => Duration::from_secs(60),
+            "h" => Duration::from_secs(60 * 60),
+            "d" => Duration::from_secs(24 * 60 * 60),
+            _ => return Err(self.unknown_unit(suffix)),
+        };
+        const NANOS_PER_SEC: u128 = 1_000_000_000;
+        unit.as_nanos()
+            .checked_mul(u128::from(n))
+            .and_then(|nanos| {
+                let secs = u64::try_from(nanos / NANOS_PER_SEC).ok()?;
+                Some(Duration::new(secs, (nanos % NANOS_PER_SEC) as u32))
+            })
+            .ok_or_else(|| ParseValueError::Overflow {
+                value: self.primary.trim().into(),
+            })
+    }
+
+    /// Parses the primary value as a number of bytes: a whole number followed
+    /// by "B" or one of the decimal units "KB" (or "kB"), "MB", "GB", "TB"
+    /// (powers of 1000) or the binary units "KiB", "MiB", "GiB", "TiB" (powers
+    /// of 1024), e.g. "256MB" or "4 KiB". A number without a unit is in bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let config_vec = configster::parse_str("max_cache = 256MB\npage = 4 KiB\nbad = 2 GiBs", ',').unwrap();
+    /// assert_eq!(config_vec[0].value.as_byte_size(), Ok(256_000_000));
+    /// assert_eq!(config_vec[1].value.as_byte_size(), Ok(4096));
+    /// assert!(config_vec[2].value.as_byte_size().is_err());
+    /// ```
+    pub fn as_byte_size(&self) -> Result<u64, ParseValueError> {
+        let (n, suffix) = self.number_and_unit()?;
+        let unit: u64 = match suffix {
+            "" | "B" => 1,
+            "KB" | "kB" => 1000,
+            "MB" => 1000_u64.pow(2),
+            "GB" => 1000_u64.pow(3),
+            "TB" => 1000_u64.pow(4),
+            "KiB" => 1 << 10,
+            "MiB" => 1 << 20,
+            "GiB" => 1 << 30,
+            "TiB" => 1 << 40,
+            _ => return Err(self.unknown_unit(suffix)),
+        };
+        n.checked_mul(unit)
+            .ok_or_else(|| ParseValueError::Overflow {
+                value: self.primary.trim().into(),
+            })
+    }
+
+    /// Splits the primary value into a whole number and the unit following
+    /// it (possibly after whitespace).
+    fn number_and_unit(&self) -> Result<(u64, &str), ParseValueError> {
+        let value = self.primary.trim();
+        let digits = value
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(value.len());
+        if digits == 0 {
+            return Err(ParseValueError::InvalidNumber {
+                value: value.into(),
+            });
+        }
+        let n = value[..digits]
+            .parse()
+            .map_err(|_| ParseValueError::Overflow {
+                value: value.into(),
+            })?;
+        Ok((n, value[digits..].trim_start()))
+    }
+
+    fn unknown_unit(&self, unit: &str) -> ParseValueError {
+        ParseValueError::UnknownUnit {
+            value: self.primary.trim().into(),
+            unit: unit.into(),
+        }
+    }
+
+    /// Joins the attributes with `delim` and a space ("a, b, c"), the way
+    /// they are written back to a configuration file. An attribute containing
+    /// the delimiter or a '"', or with whitespace around it, is put in double
+    /// quotes, with a backslash before each '"' and '\' in it, so that the
+    /// list can be read back with
+    /// [ParseOptions::quoted_values](struct.ParseOptions.html#structfield.quoted_values).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let config_vec = configster::parse_str("fonts = x, Sans, Serif", ',').unwrap();
+    /// let mut value = config_vec[0].value.clone();
+    /// assert_eq!(value.join_attributes(','), "Sans, Serif");
+    /// value.attributes.push("Noto, Bold".to_string());
+    /// assert_eq!(value.join_attributes(','), r#"Sans, Serif, "Noto, Bold""#);
+    /// ```
+    pub fn join_attributes(&self, delim: char) -> String {
+        let mut buf = [0; 4];
+        let delim: &str = delim.encode_utf8(&mut buf);
+        let mut joined = String::new();
+        for (i, attr) in self.attributes.iter().enumerate() {
+            if i > 0 {
+                joined.push_str(delim);
+                joined.push(' ');
+            }
+            // Writing to a String doesn't fail
+            let _ = write_quoted(&mut joined, attr, delim);
+        }
+        joined
+    }
+}
+
+#[cfg(feature = "std")]
+impl Value {
+    /// Returns the primary value as a path.
+    pub fn as_path(&self) -> PathBuf {
+        PathBuf::from(&self.primary)
+    }
+
+    /// Returns the primary value as a path, after expanding a leading `~` to
+    /// the home directory ($HOME) and each `${VAR}` to the value of the
+    /// environment variable `VAR`. As in a shell, an unset variable expands to
+    /// an empty string, and `${VAR:-default}` and `${VAR:+alt}` are supported
+    /// (see [interpolate](fn.interpolate.html)).
+    pub fn as_path_expanded(&self) -> PathBuf {
+        self.as_path_expanded_with(|name| std::env::var(name).ok())
+    }
+
+    /// Like [as_path_expanded](#method.as_path_expanded), but the variables
+    /// (including HOME) are looked up with `env`, which returns `None` for an
+    /// unset variable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// let env = |name: &str| match name {
+    ///     "APP_ROOT" => Some("/opt/app".to_string()),
+    ///     _ => None,
+    /// };
+    /// let config_vec = configster::parse_str("logdir = ${APP_ROOT}/logs", ',').unwrap();
+    /// assert_eq!(config_vec[0].value.as_path_expanded_with(env), Path::new("/opt/app/logs"));
+    /// ```
+    pub fn as_path_expanded_with(&self, env: impl Fn(&str) -> Option<String>) -> PathBuf {
+        let mut path = String::new();
+        let mut rest = self.primary.as_str();
+        if rest == "~" || rest.starts_with("~/") {
+            if let Some(home) = env("HOME") {
+                path.push_str(&home);
+                rest = &rest[1..];
+            }
+        }
+        let expanded = crate::interpolate::expand(
+            rest,
+            &mut |name| Ok::<_, core::convert::Infallible>(env(name)),
+            &mut |_| Ok(String::new()),
+        );
+        match expanded {
+            Ok(s) => path.push_str(&s),
+            Err(never) => match never {},
+        }
+        PathBuf::from(path)
+    }
+
+    /// Returns the primary value as a path relative to `base_dir` (usually
+    /// the directory of the configuration file); an absolute path is returned
+    /// as it is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// let filename = "/etc/app/app.conf";
+    /// let config_vec = configster::parse_str("logdir = logs\ncache = /var/cache/app", ',').unwrap();
+    /// let base_dir = Path::new(filename).parent().unwrap();
+    /// assert_eq!(config_vec[0].value.resolve_path(base_dir), Path::new("/etc/app/logs"));
+    /// assert_eq!(config_vec[1].value.resolve_path(base_dir), Path::new("/var/cache/app"));
+    /// ```
+    pub fn resolve_path(&self, base_dir: &Path) -> PathBuf {
+        base_dir.join(&self.primary)
+    }
+}
+
+/// Splits a value string using
+/// [DEFAULT_ATTR_DELIMIT_CHAR](constant.DEFAULT_ATTR_DELIMIT_CHAR.html) as the
+/// attribute delimiter; see [from_str_with](#method.from_str_with).
+///
+/// # Examples
+///
+/// ```
+/// use std::convert::TryFrom;
+/// use configster::Value;
+///
+/// let value = Value::try_from("Blue, light, shiny").unwrap();
+/// assert_eq!(value.primary, "Blue");
+/// assert_eq!(value.attributes, vec!["light", "shiny"]);
+/// ```
+impl TryFrom<&str> for Value {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Self::from_str_with(s, DEFAULT_ATTR_DELIMIT_CHAR)
+    }
+}
+
+/// Writes the option as a line of a configuration file (without a line
+/// ending), with the attributes separated by
+/// [DEFAULT_ATTR_DELIMIT_CHAR](constant.DEFAULT_ATTR_DELIMIT_CHAR.html) and a
+/// space, e.g. "option = Blue, light, shiny".
+impl fmt::Display for OptionProperties {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut delim = [0; 4];
+        write_option(
+            f,
+            self,
+            "=",
+            DEFAULT_ATTR_DELIMIT_CHAR.encode_utf8(&mut delim),
+            true,
+            true,
+        )
+    }
+}
+
+impl OptionProperties {
+    /// Returns an option with the given name and value, without a section.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use configster::OptionProperties;
+    ///
+    /// let opt_props = OptionProperties::new("max_users".into(), "30".into(), vec![]);
+    /// assert_eq!(opt_props.to_string(), "max_users = 30");
+    /// ```
+    pub fn new(option: String, primary: String, attributes: Vec<String>) -> Self {
+        Self {
+            option,
+            value: Value {
+                primary,
+                attributes,
+            },
+            section: None,
+            invalid: false,
+        }
+    }
+
+    /// Whether the option stands for an invalid line (one with whitespace in
+    /// the option name), which is named "InvalidOption_on_Line<n>". An option
+    /// that is given such a name in the file isn't invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let config_vec = configster::parse_str("max users = 30\nInvalidOption_on_Line2", ',').unwrap();
+    /// assert_eq!(config_vec[0].option, "InvalidOption_on_Line1");
+    /// assert!(config_vec[0].is_invalid());
+    /// assert!(!config_vec[1].is_invalid());
+    /// ```
+    pub fn is_invalid(&self) -> bool {
+        self.invalid
+    }
+
+    /// The fields options are compared and hashed by.
+    fn key(&self) -> (&str, &Value, Option<&str>) {
+        (&self.option, &self.value, self.section.as_deref())
+    }
+}
+
+impl PartialEq for OptionProperties {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for OptionProperties {}
+
+impl PartialOrd for OptionProperties {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OptionProperties {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl core::hash::Hash for OptionProperties {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+#[test]
+fn test_value_try_from() {
+    use alloc::vec;
+
+    let value = Value::try_from("  /home/foo ,a,, b  ").unwrap();
+    assert_eq!(value.primary, "/home/foo");
+    assert_eq!(value.attributes, vec!["a", "", "b"]);
+    let value = Value::try_from("x = y").unwrap();
+    assert_eq!(value.primary, "x = y");
+    assert!(value.attributes.is_empty());
+    let value = Value::try_from("").unwrap();
+    assert!(value.primary.is_empty() && value.attributes.is_empty());
+    let value = Value::from_str_with("1 ; 2,3", ';').unwrap();
+    assert_eq!(value.primary, "1");
+    assert_eq!(value.attributes, vec!["2,3"]);
+}
+
+#[test]
+fn test_as_duration() {
+    let duration = |s: &str| Value::try_from(s).unwrap().as_duration();
+    assert_eq!(duration("90"), Ok(Duration::from_secs(90)));
+    assert_eq!(duration(" 2h "), Ok(Duration::from_secs(7200)));
+    assert_eq!(duration("1 d"), Ok(Duration::from_secs(86400)));
+    assert_eq!(duration("0ms"), Ok(Duration::from_millis(0)));
+    assert_eq!(
+        duration("1.5h"),
+        Err(ParseValueError::UnknownUnit {
+            value: "1.5h".into(),
+            unit: ".5h".into()
+        })
+    );
+    assert_eq!(
+        duration("5S").unwrap_err().to_string(),
+        "unknown unit 'S' in '5S'"
+    );
+    for bad in &["", "s", "-5s", "+5s"] {
+        assert_eq!(
+            duration(bad),
+            Err(ParseValueError::InvalidNumber {
+                value: bad.to_string()
+            })
+        );
+    }
+    assert!(matches!(
+        duration("99999999999999999999ms"),
+        Err(ParseValueError::Overflow { .. })
+    ));
+    assert_eq!(
+        duration("5000000000d"),
+        Ok(Duration::from_secs(5_000_000_000 * 86400))
+    );
+    assert!(matches!(
+        duration("500000000000000d"),
+        Err(ParseValueError::Overflow { .. })
+    ));
+
+    let value = Value::try_from("250").unwrap();
+    assert_eq!(
+        value.as_duration_with_unit(Duration::from_millis(1)),
+        Ok(Duration::from_millis(250))
+    );
+    assert_eq!(
+        value.as_duration_with_unit(Duration::from_micros(1500)),
+        Ok(Duration::from_micros(375_000))
+    );
+}
+
+#[test]
+fn test_as_byte_size() {
+    let size = |s: &str| Value::try_from(s).unwrap().as_byte_size();
+    assert_eq!(size("512"), Ok(512));
+    assert_eq!(size("512 B"), Ok(512));
+    assert_eq!(size("10kB"), Ok(10_000));
+    assert_eq!(size("10KB"), Ok(10_000));
+    assert_eq!(size("3GB"), Ok(3_000_000_000));
+    assert_eq!(size("2TB"), Ok(2_000_000_000_000));
+    assert_eq!(size("10MiB"), Ok(10 * 1024 * 1024));
+    assert_eq!(size("1GiB"), Ok(1 << 30));
+    assert_eq!(size("1 TiB"), Ok(1 << 40));
+    assert_eq!(
+        size("10mb"),
+        Err(ParseValueError::UnknownUnit {
+            value: "10mb".into(),
+            unit: "mb".into()
+        })
+    );
+    assert_eq!(size("MB").unwrap_err().to_string(), "'MB' is not a number");
+    assert!(matches!(
+        size("1.5GB"),
+        Err(ParseValueError::UnknownUnit { .. })
+    ));
+    assert!(matches!(
+        size("20000000TiB"),
+        Err(ParseValueError::Overflow { .. })
+    ));
+}
+
+#[test]
+fn test_attribute_pairs() {
+    let value = Value::from_str_with("redis, host = a=b, =x, port=, tls", ',').unwrap();
+    assert_eq!(
+        value.attribute_pairs('='),
+        vec![
+            ("host", Some("a=b")),
+            ("", Some("x")),
+            ("port", Some("")),
+            ("tls", None)
+        ]
+    );
+    assert!(Value::from_str_with("redis", ',')
+        .unwrap()
+        .attribute_pairs('=')
+        .is_empty());
+}
+
+#[test]
+fn test_value_ord() {
+    let values: Vec<Value> = ["b", "a, z", "a", "10", "9", "a, y, z", ""]
+        .iter()
+        .map(|&v| Value::try_from(v).unwrap())
+        .collect();
+    let mut sorted = values.clone();
+    sorted.sort();
+    let sorted: Vec<(&str, &[String])> = sorted.iter().map(|v| v.tag_and_attrs()).collect();
+    let attrs = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    let (yz, z) = (attrs(&["y", "z"]), attrs(&["z"]));
+    assert_eq!(
+        sorted,
+        vec![
+            ("", &[][..]),
+            ("10", &[][..]),
+            ("9", &[][..]),
+            ("a", &[][..]),
+            ("a", &yz[..]),
+            ("a", &z[..]),
+            ("b", &[][..]),
+        ]
+    );
+    assert!(values[0] > values[1]);
+    assert_eq!(values.iter().min(), Some(&values[6]));
+}
+
+#[test]
+fn test_primary_or() {
+    let opt = OptionProperties::new("max_users".to_string(), "30".to_string(), vec![]);
+    assert_eq!(opt.value.primary_or("10"), "30");
+
+    let flag = OptionProperties::new("DelayOff".to_string(), "".to_string(), vec![]);
+    assert_eq!(flag.value.primary_or("on"), "on");
+}
+
+#[test]
+fn test_has_attr() {
+    let opt = OptionProperties::new(
+        "mount".to_string(),
+        "/media/usb".to_string(),
+        vec!["removable".to_string(), "RO".to_string()],
+    );
+    assert!(opt.value.has_attr("removable"));
+    assert!(opt.value.has_attr(" removable "));
+    assert!(!opt.value.has_attr("ro"));
+    assert!(!opt.value.has_attr("/media/usb"));
+    assert!(opt.value.has_attr_ignore_case("ro"));
+    assert!(opt.value.has_attr_ignore_case("REMOVABLE"));
+}
+
+#[test]
+fn test_split_primary() {
+    let config_vec = crate::parse_str("range = 10 - 20, open\nport = 80", ',').unwrap();
+    assert_eq!(config_vec[0].value.split_primary('-'), vec!["10", "20"]);
+    assert_eq!(config_vec[1].value.split_primary('-'), vec!["80"]);
+    assert_eq!(config_vec[0].value.split_primary(':'), vec!["10 - 20"]);
+}
+
+#[test]
+fn test_attributes_as() {
+    let config_vec = crate::parse_str("sizes = px, 1.5, -2\nflag = x", ',').unwrap();
+    assert_eq!(
+        config_vec[0].value.attributes_as::<f64>(),
+        Ok(vec![1.5, -2.0])
+    );
+    assert_eq!(
+        config_vec[0].value.attributes_as::<u32>(),
+        Err((0, "1.5".to_string()))
+    );
+    assert_eq!(config_vec[1].value.attributes_as::<u8>(), Ok(vec![]));
+}
+
+#[test]
+fn test_sort_dedup() {
+    let mut config_vec =
+        crate::parse_str("b = 1\na = 2\na = 1, y\na = 1, x\nb = 1\na = 1", ',').unwrap();
+    config_vec.sort();
+    config_vec.dedup();
+    let sorted: Vec<alloc::string::String> = config_vec.iter().map(|o| o.to_string()).collect();
+    assert_eq!(
+        sorted,
+        vec!["a = 1", "a = 1, x", "a = 1, y", "a = 2", "b = 1"]
+    );
+}
+
+#[test]
+fn test_option_display() {
+    use alloc::string::ToString;
+
+    let config_vec = crate::parse_str("a=Blue;light ; shiny\nDelayOff\nb = ;x", ';').unwrap();
+    assert_eq!(config_vec[0].to_string(), "a = Blue, light, shiny");
+    assert_eq!(config_vec[1].to_string(), "DelayOff");
+    assert_eq!(config_vec[2].to_string(), "b = , x");
+}
+
+#[test]
+fn test_is_truthy() {
+    let config_vec =
+        crate::parse_str("a = ON\nb = enabled, x\nc = 0\nDelayOff\nd = si", ',').unwrap();
+    let truthy: Vec<bool> = config_vec.iter().map(|o| o.value.is_truthy()).collect();
+    assert_eq!(truthy, vec![true, true, false, false, false]);
+    assert!(config_vec[4].value.is_truthy_in(&["si", "oui"]));
+    assert!(!config_vec[0].value.is_truthy_in(&["si", "oui"]));
+}
+
+#[test]
+fn test_attributes_or_default() {
+    let config_vec = crate::parse_str("mode = fast\nempty = x,\nDelayOff", ',').unwrap();
+    assert_eq!(
+        config_vec[0].value.attributes_or_default(&["a", "b"]),
+        vec!["a", "b"]
+    );
+    assert_eq!(config_vec[1].value.attributes_or_default(&["a"]), vec![""]);
+    assert!(config_vec[2].value.attributes_or_default(&[]).is_empty());
+}
+
+#[test]
+fn test_dedup_attributes() {
+    let mut config_vec = crate::parse_str("tags = x, red, red, green, red, Green", ',').unwrap();
+    config_vec[0].value.dedup_attributes();
+    assert_eq!(
+        config_vec[0].value.attributes,
+        vec!["red", "green", "Green"]
+    );
+
+    // Many attributes, most of them repeated, in first-occurrence order
+    let mut value = Value {
+        primary: String::new(),
+        attributes: (0..10_000)
+            .map(|i| alloc::format!("{}", i % 7 * 3 % 7))
+            .collect(),
+    };
+    value.dedup_attributes();
+    assert_eq!(value.attributes, ["0", "3", "6", "2", "5", "1", "4"]);
+}
+
+#[test]
+fn test_all_values() {
+    let config_vec =
+        crate::parse_str("colors = red, green, blue\nempty = , green\nDelayOff", ',').unwrap();
+    assert_eq!(
+        config_vec[0].value.all_values(),
+        vec!["red", "green", "blue"]
+    );
+    assert_eq!(config_vec[1].value.all_values(), vec!["green"]);
+    assert!(config_vec[2].value.all_values().is_empty());
+}
+
+#[test]
+fn test_clone_and_hash() {
+    use std::collections::HashSet;
+
+    let config_vec = crate::parse_str("max_users = 30\nDelayOff\nmax_users = 30", ',').unwrap();
+    let copy = config_vec.clone();
+    assert_eq!(copy, config_vec);
+
+    let set: HashSet<OptionProperties> = copy.into_iter().collect();
+    assert_eq!(set.len(), 2);
+    assert!(set.contains(&config_vec[1]));
+}
+
+#[test]
+fn test_primary_trimmed_of() {
+    let value = Value::try_from("''x'' y'").unwrap();
+    assert_eq!(value.primary_trimmed_of(&['\'']), "x'' y");
+    assert_eq!(value.primary_trimmed_of(&['\'', 'x']), " y");
+    assert_eq!(value.primary_trimmed_of(&[]), value.primary);
+    assert_eq!(
+        Value::try_from("\"\"").unwrap().primary_trimmed_of(&['"']),
+        ""
+    );
+}
+
+#[test]
+fn test_tag_and_attrs() {
+    let value = Value::try_from("label, text=Hi").unwrap();
+    assert_eq!(value.tag_and_attrs(), ("label", &value.attributes[..]));
+    assert_eq!(value.tag_and_attrs().1, ["text=Hi"]);
+    let value = Value::try_from("").unwrap();
+    assert_eq!(value.tag_and_attrs(), ("", &[][..]));
+}
+
+#[test]
+fn test_join_attributes() {
+    let value = Value::from_str_with("x; a ;b;;c", ';').unwrap();
+    assert_eq!(value.join_attributes(';'), "a; b; ; c");
+    assert_eq!(value.join_attributes(','), "a, b, , c");
+    let value = Value {
+        primary: String::new(),
+        attributes: ["a;b", r#"say "hi""#, r"c:\dir", r#"\;""#]
+            .iter()
+            .map(|&a| a.into())
+            .collect(),
+    };
+    assert_eq!(
+        value.join_attributes(';'),
+        r#""a;b"; "say \"hi\""; c:\dir; "\\;\"""#
+    );
+    assert_eq!(Value::try_from("x").unwrap().join_attributes(','), "");
+
+    // The quoted attributes read back with quoted_values
+    let mut value = value;
+    value.attributes.push(" padded ".into());
+    let line = alloc::format!("x = y; {}", value.join_attributes(';'));
+    let opts = ParseOptions {
+        attr_delimiter: crate::Delimiter::Char(';'),
+        quoted_values: true,
+        ..ParseOptions::default()
+    };
+    let config_vec = crate::parse_str_with(&line, &opts).unwrap();
+    assert_eq!(config_vec[0].value.attributes, value.attributes);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_resolve_path() {
+    let config_vec = crate::parse_str("a = x/y.log\nb = /abs\nc = ../up", ',').unwrap();
+    let base = Path::new("conf.d");
+    assert_eq!(
+        config_vec[0].value.resolve_path(base),
+        Path::new("conf.d/x/y.log")
+    );
+    assert_eq!(config_vec[1].value.resolve_path(base), Path::new("/abs"));
+    assert_eq!(
+        config_vec[2].value.resolve_path(base),
+        Path::new("conf.d/../up")
+    );
+    assert_eq!(
+        config_vec[0].value.resolve_path(Path::new("")),
+        Path::new("x/y.log")
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_as_path() {
+    // The environment isn't changed, since the tests run in parallel
+    let env = |name: &str| match name {
+        "HOME" => Some("/home/foo".to_string()),
+        "CONFIGSTER_TEST_DIR" => Some("Documents".to_string()),
+        _ => None,
+    };
+    let config_vec = crate::parse_str(
+        "a = ~/${CONFIGSTER_TEST_DIR}/x\nb = ~\nc = /srv/~user${CONFIGSTER_TEST_UNSET}\nd = ~user/x",
+        ',',
+    )
+    .unwrap();
+    assert_eq!(
+        config_vec[0].value.as_path(),
+        Path::new("~/${CONFIGSTER_TEST_DIR}/x")
+    );
+    assert_eq!(
+        config_vec[0].value.as_path_expanded_with(env),
+        Path::new("/home/foo/Documents/x")
+    );
+    assert_eq!(
+        config_vec[1].value.as_path_expanded_with(env),
+        Path::new("/home/foo")
+    );
+    assert_eq!(
+        config_vec[2].value.as_path_expanded_with(env),
+        Path::new("/srv/~user")
+    );
+    assert_eq!(
+        config_vec[3].value.as_path_expanded_with(env),
+        Path::new("~user/x")
+    );
+}
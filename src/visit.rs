@@ -0,0 +1,121 @@
+use crate::parse::LineParser;
+use crate::{Entry, OptionProperties, ParseError, ParseOptions};
+
+/// Receives the options of a configuration as they are parsed, along with
+/// their line numbers; see [visit_str](fn.visit_str.html) and
+/// [visit_file](fn.visit_file.html). An option's section is in its `section`
+/// field.
+///
+/// Any `FnMut(OptionProperties, usize)` closure is a `Visitor`.
+pub trait Visitor {
+    /// Called for each option, with the line number it starts on.
+    fn visit_option(&mut self, option: OptionProperties, line: usize);
+
+    /// Called for each section header, with its line number. Does nothing by
+    /// default.
+    fn visit_section(&mut self, _name: &str, _line: usize) {}
+}
+
+impl<F: FnMut(OptionProperties, usize)> Visitor for F {
+    fn visit_option(&mut self, option: OptionProperties, line: usize) {
+        self(option, line)
+    }
+}
+
+/// Parses configuration data held in a string, passing each option to the
+/// visitor instead of collecting them into a vector. Parsing stops at the
+/// first error.
+///
+/// # Examples
+///
+/// ```
+/// use configster::ParseOptions;
+///
+/// #[derive(Default)]
+/// struct Settings {
+///     max_users: u32,
+///     delay: bool,
+/// }
+///
+/// let mut settings = Settings { delay: true, ..Settings::default() };
+/// configster::visit_str("max_users = 30\nDelayOff", &ParseOptions::default(), &mut |opt: configster::OptionProperties, _line| {
+///     match opt.option.as_str() {
+///         "max_users" => settings.max_users = opt.value.primary.parse().unwrap(),
+///         "DelayOff" => settings.delay = false,
+///         _ => {}
+///     }
+/// })
+/// .unwrap();
+/// assert_eq!(settings.max_users, 30);
+/// assert!(!settings.delay);
+/// ```
+pub fn visit_str<V: Visitor + ?Sized>(
+    s: &str,
+    opts: &ParseOptions,
+    visitor: &mut V,
+) -> Result<(), ParseError> {
+    visit(LineParser::new(s.lines().map(Ok), opts), visitor)
+}
+
+pub(crate) fn visit<I, S, V>(
+    mut parser: LineParser<'_, '_, I>,
+    visitor: &mut V,
+) -> Result<(), ParseError>
+where
+    I: Iterator<Item = Result<S, ParseError>>,
+    S: AsRef<str>,
+    V: Visitor + ?Sized,
+{
+    while let Some(entry) = parser.next() {
+        match entry? {
+            Entry::Option(opt_props) => visitor.visit_option(opt_props, parser.line()),
+            Entry::Section(name) => visitor.visit_section(&name, parser.line()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_visit_str() {
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+
+    #[derive(Default)]
+    struct Collect {
+        seen: Vec<(Option<String>, String, usize)>,
+        sections: Vec<(String, usize)>,
+    }
+
+    impl Visitor for Collect {
+        fn visit_option(&mut self, option: OptionProperties, line: usize) {
+            self.seen.push((option.section, option.option, line));
+        }
+
+        fn visit_section(&mut self, name: &str, line: usize) {
+            self.sections.push((name.to_string(), line));
+        }
+    }
+
+    let data = "a = 1\n# comment\nscript = <<END\necho\nEND\n\n[net]\nb = 2";
+    let opts = ParseOptions {
+        block_values: true,
+        ..ParseOptions::default()
+    };
+    let mut v = Collect::default();
+    visit_str(data, &opts, &mut v).unwrap();
+    assert_eq!(
+        v.seen,
+        vec![
+            (None, "a".to_string(), 1),
+            (None, "script".to_string(), 3),
+            (Some("net".to_string()), "b".to_string(), 8),
+        ]
+    );
+    assert_eq!(v.sections, vec![("net".to_string(), 7)]);
+
+    let mut count = 0;
+    let err = visit_str("a = 1\nb = <<END", &opts, &mut |_, _| count += 1);
+    assert!(err.is_err());
+    assert_eq!(count, 1);
+}
@@ -0,0 +1,235 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::parse::LineParser;
+use crate::{Entry, OptionProperties, ParseError, ParseOptions};
+
+/// A likely mistake in a configuration that doesn't stop it from being
+/// parsed; see [parse_str_with_warnings](fn.parse_str_with_warnings.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Warning {
+    /// A section header appears again; `first_line` is the line of the first
+    /// header.
+    RepeatedSection {
+        name: String,
+        line: usize,
+        first_line: usize,
+    },
+    /// An option in a repeated section was already set in an earlier block
+    /// of the same section.
+    ShadowedOption {
+        section: String,
+        option: String,
+        line: usize,
+        first_line: usize,
+    },
+    /// The option name has whitespace around it in the line, with
+    /// [ParseOptions::warn_key_whitespace](struct.ParseOptions.html#structfield.warn_key_whitespace)
+    /// set.
+    KeyWhitespace { option: String, line: usize },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::RepeatedSection {
+                name,
+                line,
+                first_line,
+            } => write!(
+                f,
+                "line {}: section '{}' already appeared on line {}",
+                line, name, first_line
+            ),
+            Warning::ShadowedOption {
+                section,
+                option,
+                line,
+                first_line,
+            } => write!(
+                f,
+                "line {}: option '{}' in section '{}' shadows the one on line {}",
+                line, option, section, first_line
+            ),
+            Warning::KeyWhitespace { option, line } => {
+                write!(
+                    f,
+                    "line {}: whitespace around option name '{}'",
+                    line, option
+                )
+            }
+        }
+    }
+}
+
+/// Like [parse_str_with](fn.parse_str_with.html), but also returns a list of
+/// [Warning](enum.Warning.html)s for repeated section headers, and for options
+/// in a repeated section that shadow the same option in an earlier block (and,
+/// if enabled, for whitespace around option names).
+///
+/// # Examples
+///
+/// ```
+/// use configster::{ParseOptions, Warning};
+///
+/// let data = "[db]\nport = 5432\n[web]\nport = 80\n[db]\nport = 6543";
+/// let (config_vec, warnings) = configster::parse_str_with_warnings(data, &ParseOptions::default()).unwrap();
+/// assert_eq!(config_vec.len(), 3);
+/// assert_eq!(warnings.len(), 2);
+/// assert_eq!(warnings[1].to_string(), "line 6: option 'port' in section 'db' shadows the one on line 2");
+/// ```
+pub fn parse_str_with_warnings(
+    s: &str,
+    opts: &ParseOptions,
+) -> Result<(Vec<OptionProperties>, Vec<Warning>), ParseError> {
+    collect_with_warnings(LineParser::new(s.lines().map(Ok), opts))
+}
+
+pub(crate) fn collect_with_warnings<I, S>(
+    mut parser: LineParser<'_, '_, I>,
+) -> Result<(Vec<OptionProperties>, Vec<Warning>), ParseError>
+where
+    I: Iterator<Item = Result<S, ParseError>>,
+    S: AsRef<str>,
+{
+    let mut vec = Vec::new();
+    let mut warnings = Vec::new();
+    // The line of the first header of each section
+    let mut sections: BTreeMap<String, usize> = BTreeMap::new();
+    // The line of the first occurrence of each (section, option)
+    let mut options: BTreeMap<(String, String), usize> = BTreeMap::new();
+    // The line where the current block of a repeated section starts
+    let mut repeat_start: Option<usize> = None;
+
+    while let Some(entry) = parser.next() {
+        let line = parser.line();
+        match entry? {
+            Entry::Section(name) => match sections.get(&name) {
+                Some(&first_line) => {
+                    warnings.push(Warning::RepeatedSection {
+                        name,
+                        line,
+                        first_line,
+                    });
+                    repeat_start = Some(line);
+                }
+                None => {
+                    sections.insert(name, line);
+                    repeat_start = None;
+                }
+            },
+            Entry::Option(opt_props) => {
+                if parser.key_whitespace() {
+                    warnings.push(Warning::KeyWhitespace {
+                        option: opt_props.option.clone(),
+                        line,
+                    });
+                }
+                if let Some(section) = &opt_props.section {
+                    let key = (section.clone(), opt_props.option.clone());
+                    match options.get(&key) {
+                        Some(&first_line) if repeat_start.is_some_and(|s| first_line < s) => {
+                            warnings.push(Warning::ShadowedOption {
+                                section: section.to_string(),
+                                option: opt_props.option.clone(),
+                                line,
+                                first_line,
+                            });
+                        }
+                        Some(_) => {}
+                        None => {
+                            options.insert(key, line);
+                        }
+                    }
+                }
+                vec.push(opt_props);
+            }
+            _ => {}
+        }
+    }
+    Ok((vec, warnings))
+}
+
+#[test]
+fn test_parse_str_with_warnings() {
+    let data = "\
+port = 1
+[db]
+port = 5432
+host = a
+[web]
+port = 80
+[db]
+user = me
+port = 6543
+[db]
+host = b
+port = 7654
+[web]
+";
+    let (config_vec, warnings) = parse_str_with_warnings(data, &ParseOptions::default()).unwrap();
+    assert_eq!(config_vec.len(), 8);
+    let shadowed = |option: &str, line, first_line| Warning::ShadowedOption {
+        section: "db".to_string(),
+        option: option.to_string(),
+        line,
+        first_line,
+    };
+    let repeated = |name: &str, line, first_line| Warning::RepeatedSection {
+        name: name.to_string(),
+        line,
+        first_line,
+    };
+    assert_eq!(
+        warnings,
+        vec![
+            repeated("db", 7, 2),
+            shadowed("port", 9, 3),
+            repeated("db", 10, 2),
+            shadowed("host", 11, 4),
+            shadowed("port", 12, 3),
+            repeated("web", 13, 5),
+        ]
+    );
+
+    // A repeated option within a single block isn't reported
+    let (_, warnings) =
+        parse_str_with_warnings("[db]\nport = 1\nport = 2", &ParseOptions::default()).unwrap();
+    assert!(warnings.is_empty());
+}
+
+#[test]
+fn test_key_whitespace_warnings() {
+    let data = "a=1\nb = 2\n  c=3\n\td\ne\t\nf=4 , x=y";
+    let (_, warnings) = parse_str_with_warnings(data, &ParseOptions::default()).unwrap();
+    assert!(warnings.is_empty());
+
+    let opts = ParseOptions {
+        warn_key_whitespace: true,
+        ..ParseOptions::default()
+    };
+    let (config_vec, warnings) = parse_str_with_warnings(data, &opts).unwrap();
+    assert_eq!(config_vec.len(), 6);
+    let lines: Vec<(&str, usize)> = warnings
+        .iter()
+        .map(|w| match w {
+            Warning::KeyWhitespace { option, line } => (option.as_str(), *line),
+            w => panic!("unexpected warning {:?}", w),
+        })
+        .collect();
+    assert_eq!(lines, vec![("b", 2), ("c", 3), ("d", 4), ("e", 5)]);
+    assert_eq!(
+        warnings[0].to_string(),
+        "line 2: whitespace around option name 'b'"
+    );
+
+    let opts = ParseOptions {
+        warn_key_whitespace: true,
+        whitespace_separator: true,
+        ..ParseOptions::default()
+    };
+    let (_, warnings) = parse_str_with_warnings("a 1\n b 2\nc=3", &opts).unwrap();
+    assert_eq!(warnings.len(), 1);
+}
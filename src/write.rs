@@ -0,0 +1,381 @@
+use alloc::string::String;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+#[cfg(feature = "std")]
+use crate::parse::LineParser;
+use crate::OptionProperties;
+#[cfg(feature = "std")]
+use crate::{Delimiter, Entry, IncludeKind, ParseError, ParseOptions};
+
+/// Writes the options in the configuration file format, one per line, with
+/// the attributes separated by `attr_delimiter` and a space. Since there is
+/// no header for leaving a section, the options without one are written
+/// first; a section header is written wherever the section changes after
+/// that.
+pub(crate) fn write_options<W: fmt::Write>(
+    w: &mut W,
+    vec: &[OptionProperties],
+    attr_delimiter: &str,
+) -> fmt::Result {
+    let top_level = vec.iter().filter(|o| o.section.is_none());
+    let in_sections = vec.iter().filter(|o| o.section.is_some());
+    let mut section: Option<&str> = None;
+    for (i, opt_props) in top_level.chain(in_sections).enumerate() {
+        let opt_section = opt_props.section.as_deref();
+        if opt_section != section {
+            if let Some(name) = opt_section {
+                if i > 0 {
+                    writeln!(w)?;
+                }
+                writeln!(w, "[{}]", name)?;
+            }
+            section = opt_section;
+        }
+        write_option(w, opt_props, "=", attr_delimiter, true, true)?;
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+/// Writes a single option line (without the line ending). A primary value
+/// of several lines is written as a block value, as is one starting with "<<"
+/// with `blocks` (for a reader with `block_values`), and with `quote` the
+/// values are quoted as needed (see [write_quoted]). The options that can't
+/// be written this way are rejected by [check_writable].
+pub(crate) fn write_option<W: fmt::Write>(
+    w: &mut W,
+    opt_props: &OptionProperties,
+    separator: &str,
+    attr_delimiter: &str,
+    quote: bool,
+    blocks: bool,
+) -> fmt::Result {
+    let value = &opt_props.value;
+    w.write_str(&opt_props.option)?;
+    if value.primary.is_empty() && value.attributes.is_empty() {
+        return Ok(());
+    }
+    let block = value.primary.contains('\n') || (blocks && value.primary.starts_with("<<"));
+    if block && value.attributes.is_empty() {
+        // An end marker that isn't one of the lines
+        let mut marker = String::from("END");
+        while value.primary.lines().any(|l| l == marker) {
+            marker.push('_');
+        }
+        return write!(
+            w,
+            " {} <<{}\n{}\n{}",
+            separator, marker, value.primary, marker
+        );
+    }
+    let write_value = |w: &mut W, s: &str| match quote {
+        true => write_quoted(w, s, attr_delimiter),
+        false => w.write_str(s),
+    };
+    write!(w, " {} ", separator)?;
+    write_value(w, &value.primary)?;
+    for attr in &value.attributes {
+        write!(w, "{} ", attr_delimiter)?;
+        write_value(w, attr)?;
+    }
+    Ok(())
+}
+
+/// Writes `s`, in double quotes (with a backslash before each '"' and '\'
+/// in it) if it couldn't be read back as it is: when it contains the
+/// delimiter (whitespace, for an empty one) or a '"', or starts or ends with
+/// whitespace. See `ParseOptions::quoted_values`.
+pub(crate) fn write_quoted<W: fmt::Write>(w: &mut W, s: &str, delim: &str) -> fmt::Result {
+    let needs_quotes = s.contains('"')
+        || s.trim() != s
+        || if delim.is_empty() {
+            s.is_empty() || s.contains(char::is_whitespace)
+        } else {
+            s.contains(delim)
+        };
+    if !needs_quotes {
+        return w.write_str(s);
+    }
+    w.write_char('"')?;
+    for c in s.chars() {
+        if c == '"' || c == '\\' {
+            w.write_char('\\')?;
+        }
+        w.write_char(c)?;
+    }
+    w.write_char('"')
+}
+
+/// Returns an error for an option that can't be written so that it reads
+/// back the same: an option name that is empty, contains whitespace or the
+/// separator, or starts with '#' or '['; a section name with a line break or
+/// whitespace around it; or a line break in a value that has attributes.
+#[cfg(feature = "std")]
+pub(crate) fn check_writable(opt_props: &OptionProperties, separator: &str) -> io::Result<()> {
+    let option = &opt_props.option;
+    let value = &opt_props.value;
+    let problem = if option.is_empty()
+        || option.contains(char::is_whitespace)
+        || option.contains(separator)
+        || option.starts_with(&['#', '['][..])
+    {
+        "the option name can't be written"
+    } else if opt_props
+        .section
+        .as_ref()
+        .is_some_and(|s| s.contains('\n') || s.trim() != s)
+    {
+        "the section name can't be written"
+    } else if !value.attributes.is_empty()
+        && (value.primary.contains('\n') || value.attributes.iter().any(|a| a.contains('\n')))
+    {
+        "a value with attributes can't span several lines"
+    } else {
+        return Ok(());
+    };
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        alloc::format!("option {:?}: {}", option, problem),
+    ))
+}
+
+/// Writes options in the configuration file format, so that parsing the
+/// output with the same delimiter, `ParseOptions::quoted_values` and
+/// `ParseOptions::block_values` gives the options back. The options without a section are written first, since
+/// there is no header for leaving a section; then a section header is
+/// written wherever the section changes. The values that contain the
+/// delimiter or a '"', or have whitespace around them, are written in double
+/// quotes, and values of several lines as block values.
+///
+/// An option that can't be written this way (e.g. an option name with
+/// whitespace, or a value of several lines with attributes) is an error of
+/// kind `InvalidInput`, and nothing is written then. The `Display`
+/// implementation of [Config](struct.Config.html) writes the same format,
+/// but without this check.
+///
+/// # Examples
+///
+/// ```
+/// let config_vec = configster::parse_str("option = Blue, light, shiny\nDelayOff", ',').unwrap();
+/// let mut out = Vec::new();
+/// configster::write_to(&config_vec, &mut out, ',').unwrap();
+/// assert_eq!(out, b"option = Blue, light, shiny\nDelayOff\n");
+/// ```
+#[cfg(feature = "std")]
+pub fn write_to<W: io::Write>(
+    vec: &[OptionProperties],
+    writer: &mut W,
+    attr_delimit_char: char,
+) -> io::Result<()> {
+    vec.iter().try_for_each(|o| check_writable(o, "="))?;
+    let mut s = String::new();
+    let mut delim = [0; 4];
+    // Writing to a String doesn't fail
+    let _ = write_options(&mut s, vec, attr_delimit_char.encode_utf8(&mut delim));
+    writer.write_all(s.as_bytes())
+}
+
+/// Reformats a configuration: the option lines are written in the canonical
+/// form (see [write_to](fn.write_to.html)), with trailing whitespace removed,
+/// and everything else is kept as it is, including comment lines and blank
+/// lines. Lines that aren't valid options are kept unchanged. Inline and block
+/// comments aren't kept.
+#[cfg(feature = "std")]
+pub(crate) fn format_str(s: &str, opts: &ParseOptions) -> Result<String, ParseError> {
+    let lines: alloc::vec::Vec<&str> = s.lines().collect();
+    let mut parser = LineParser::new(lines.iter().map(Ok), opts);
+    let mut delim = [0; 4];
+    let attr_delimiter: &str = match &opts.attr_delimiter {
+        Delimiter::Char(c) => c.encode_utf8(&mut delim),
+        Delimiter::Str(d) => d,
+        Delimiter::Whitespace => "",
+    };
+    let separator = match opts.separator {
+        "" => " ",
+        sep => sep,
+    };
+
+    let mut out = String::with_capacity(s.len());
+    while let Some(entry) = parser.next() {
+        let raw = lines[parser.line() - 1];
+        // Writing to a String doesn't fail
+        let _ = match entry? {
+            Entry::Option(opt_props) if opt_props.is_invalid() => {
+                fmt::Write::write_str(&mut out, raw.trim())
+            }
+            Entry::Raw(line) => fmt::Write::write_str(&mut out, line.trim_end()),
+            Entry::Option(opt_props) => write_option(
+                &mut out,
+                &opt_props,
+                separator,
+                attr_delimiter,
+                opts.quoted_values,
+                opts.block_values,
+            ),
+            Entry::Section(name) => fmt::Write::write_fmt(&mut out, format_args!("[{}]", name)),
+            Entry::Comment(_) => fmt::Write::write_str(&mut out, raw.trim_end()),
+            Entry::Blank => Ok(()),
+            Entry::Include(include) => {
+                let keyword = match include.kind {
+                    IncludeKind::File => "include",
+                    IncludeKind::IfExists => "include_if_exists",
+                    IncludeKind::Env => "include_env",
+                };
+                fmt::Write::write_fmt(&mut out, format_args!("{} {}", keyword, include.target))
+            }
+            Entry::Directive(directive) => {
+                fmt::Write::write_fmt(&mut out, format_args!("@{}", directive.name)).and_then(
+                    |_| {
+                        directive.args.iter().try_for_each(|a| {
+                            fmt::Write::write_fmt(&mut out, format_args!(" {}", a))
+                        })
+                    },
+                )
+            }
+        };
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_format_str() {
+    let opts = ParseOptions {
+        includes: true,
+        directives: true,
+        block_values: true,
+        ..ParseOptions::default()
+    };
+    let data =
+        "# Users  \n  ##  max  \nmax_users=30\t\n\n   \n  [ db ]\nport   =  5432 ,tcp,  local  \n\
+                DelayOff  \nHello World = bad option \nscript = <<EOF\necho\nEND\nEOF\n\
+                include   conf.d/x.conf\n@override  a   b";
+    let formatted = format_str(data, &opts).unwrap();
+    assert_eq!(
+        formatted,
+        "# Users\n  ##  max\nmax_users = 30\n\n\n[db]\nport = 5432, tcp, local\nDelayOff\n\
+         Hello World = bad option\nscript = <<END_\necho\nEND\nEND_\ninclude conf.d/x.conf\n\
+         @override a b\n"
+    );
+    // Formatting is idempotent and doesn't change the options
+    assert_eq!(format_str(&formatted, &opts).unwrap(), formatted);
+    assert_eq!(
+        crate::parse_str_with(&formatted, &opts).unwrap(),
+        crate::parse_str_with(data, &opts).unwrap()
+    );
+
+    let opts = ParseOptions {
+        separator: "=>",
+        ..ParseOptions::with_delimiter(" :: ")
+    };
+    assert_eq!(format_str("a=>b :: c", &opts).unwrap(), "a => b ::  c\n");
+
+    // Raw lines keep their indentation
+    let opts = ParseOptions {
+        raw_invalid_lines: true,
+        ..ParseOptions::default()
+    };
+    assert_eq!(
+        format_str("  match host * {  \na=1", &opts).unwrap(),
+        "  match host * {\na = 1\n"
+    );
+}
+
+#[test]
+fn test_write_options() {
+    use alloc::string::String;
+
+    let data = "a = 1\nDelayOff\nempty = , x\n[db]\nport = 5432, tcp, local\n[web]\nport = 80";
+    let config_vec = crate::parse_str(data, ',').unwrap();
+    let mut s = String::new();
+    write_options(&mut s, &config_vec, ";").unwrap();
+    assert_eq!(
+        s,
+        "a = 1\nDelayOff\nempty = ; x\n\n[db]\nport = 5432; tcp; local\n\n[web]\nport = 80\n"
+    );
+    assert_eq!(crate::parse_str(&s, ';').unwrap(), config_vec);
+}
+
+#[test]
+fn test_write_options_quoted() {
+    let mut vec = crate::parse_str("a = 1, x", ',').unwrap();
+    vec[0].value.attributes.push("y, z".into());
+    vec[0].value.attributes.push(" w".into());
+    let mut out = String::new();
+    write_options(&mut out, &vec, ",").unwrap();
+    assert_eq!(out, "a = 1, x, \"y, z\", \" w\"\n");
+    let opts = crate::ParseOptions {
+        quoted_values: true,
+        ..crate::ParseOptions::default()
+    };
+    assert_eq!(crate::parse_str_with(&out, &opts).unwrap(), vec);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_write_to_escapes_values() {
+    let opts = crate::ParseOptions {
+        quoted_values: true,
+        block_values: true,
+        ..crate::ParseOptions::default()
+    };
+    let new = |option: &str, primary: &str, attributes: &[&str]| {
+        let attributes = attributes.iter().map(|&a| a.into()).collect();
+        OptionProperties::new(option.into(), primary.into(), attributes)
+    };
+    let vec = alloc::vec![
+        new("comma", "a, b", &[]),
+        new("quote", r#""x" y"#, &["z"]),
+        new("marker", "<<END", &[]),
+        new("marker_attr", "<<END", &["x"]),
+        new("padded", " p ", &[]),
+        new("lines", "one\nEND\ntwo", &[]),
+    ];
+    let mut out = Vec::new();
+    write_to(&vec, &mut out, ',').unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with("comma = \"a, b\"\nquote = \"\\\"x\\\" y\", z\n"));
+    assert_eq!(crate::parse_str_with(&out, &opts).unwrap(), vec);
+
+    // The options that can't be read back are an error, before writing
+    for bad in [
+        new("a b", "1", &[]),
+        new("a=b", "1", &[]),
+        new("#a", "1", &[]),
+        new("", "1", &[]),
+        new("a", "one\ntwo", &["x"]),
+        new("a", "1", &["x\ny"]),
+    ] {
+        let mut out = Vec::new();
+        let err = write_to(&[new("ok", "1", &[]), bad], &mut out, ',').unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(out.is_empty());
+    }
+    let mut sectioned = new("a", "1", &[]);
+    sectioned.section = Some("db\n".into());
+    assert!(write_to(&[sectioned], &mut Vec::new(), ',').is_err());
+}
+
+#[test]
+fn test_write_options_top_level_last() {
+    let mut vec = crate::parse_str("a = 1\n[db]\nport = 5432\n[web]\nport = 80", ',').unwrap();
+    vec.push(OptionProperties::new("b".into(), "2".into(), alloc::vec![]));
+    vec.insert(
+        1,
+        OptionProperties::new("c".into(), "".into(), alloc::vec![]),
+    );
+    vec[3].section = None;
+    let mut out = String::new();
+    write_options(&mut out, &vec, ",").unwrap();
+    assert_eq!(out, "a = 1\nc\nport = 80\nb = 2\n\n[db]\nport = 5432\n");
+
+    // The sections of the options are kept
+    let mut parsed = crate::parse_str(&out, ',').unwrap();
+    let key = |o: &OptionProperties| (o.section.clone(), o.option.clone());
+    parsed.sort_by_key(key);
+    vec.sort_by_key(key);
+    assert_eq!(parsed, vec);
+}
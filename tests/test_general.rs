@@ -2,3 +2,92 @@
 fn test_get_version() {
     assert_eq!(&configster::get_ver()[..3], "0.1");
 }
+
+#[cfg(feature = "std")]
+#[test]
+fn test_parse_file_error_has_path() {
+    let err = configster::parse_file("./no_such_file.conf", ',').unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    assert!(err.to_string().starts_with("./no_such_file.conf: "));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_parse_with_default() {
+    let defaults = "max_users = 10\nsocket = /run/app.sock";
+
+    let config_vec = configster::parse_with_default(defaults, "./config_test.conf", ',').unwrap();
+    let max_users: Vec<&str> = config_vec
+        .iter()
+        .filter(|o| o.option == "max_users")
+        .map(|o| o.value.primary.as_str())
+        .collect();
+    assert_eq!(max_users, vec!["30"]);
+    assert!(config_vec.iter().any(|o| o.option == "socket"));
+    assert!(config_vec.iter().any(|o| o.option == "DelayOff"));
+
+    let config_vec = configster::parse_with_default(defaults, "./no_such_file.conf", ',').unwrap();
+    assert_eq!(config_vec, configster::parse_str(defaults, ',').unwrap());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_parse_file_entries() {
+    use configster::{Entry, ParseOptions};
+
+    let entries =
+        configster::parse_file_entries("./config_test.conf", &ParseOptions::default()).unwrap();
+    assert_eq!(entries.len(), 8);
+    match &entries[1] {
+        Entry::Comment(c) => {
+            assert_eq!(c.text, "option = nothing, void, empty");
+            assert_eq!(c.raw, "# option = nothing, void, empty");
+        }
+        e => panic!("expected a comment, got {:?}", e),
+    }
+    assert_eq!(entries[3], Entry::Blank);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_visit_file() {
+    let mut lines = Vec::new();
+    configster::visit_file(
+        "./config_test.conf",
+        &configster::ParseOptions::default(),
+        &mut |opt: configster::OptionProperties, line| lines.push((opt.option, line)),
+    )
+    .unwrap();
+    assert_eq!(
+        lines,
+        vec![
+            ("option".to_string(), 1),
+            ("max_users".to_string(), 3),
+            ("DelayOff".to_string(), 5),
+            ("InvalidOption_on_Line8".to_string(), 8),
+        ]
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_hash_set_dedup() {
+    use std::collections::HashSet;
+
+    // The same options, read from two files
+    let mut config_vec = configster::parse_file("./config_test.conf", ',').unwrap();
+    config_vec.extend(
+        configster::parse_str(
+            "max_users = 30\n[extra]\nmax_users = 30\noption = Blue, light",
+            ',',
+        )
+        .unwrap(),
+    );
+    let set: HashSet<&configster::OptionProperties> = config_vec.iter().collect();
+    // The option in a section, and the one with fewer attributes, are kept
+    assert_eq!(set.len(), 6);
+    assert_eq!(set.iter().filter(|o| o.option == "max_users").count(), 2);
+
+    let values: HashSet<&configster::Value> = config_vec.iter().map(|o| &o.value).collect();
+    assert_eq!(values.len(), 4);
+}
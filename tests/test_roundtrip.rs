@@ -0,0 +1,46 @@
+#![cfg(feature = "std")]
+
+use configster::OptionProperties;
+use proptest::prelude::*;
+
+/// A primary value or attribute: words of characters with no meaning to the
+/// parser, separated by single spaces.
+fn value_string() -> impl Strategy<Value = String> {
+    "([A-Za-z0-9_./:-]+( [A-Za-z0-9_./:-]+)*)?"
+}
+
+fn option(section: Option<String>) -> impl Strategy<Value = OptionProperties> {
+    (
+        "[A-Za-z_][A-Za-z0-9_.-]{0,12}",
+        value_string(),
+        prop::collection::vec(value_string(), 0..5),
+    )
+        .prop_map(move |(option, primary, attributes)| {
+            let mut opt_props = OptionProperties::new(option, primary, attributes);
+            opt_props.section = section.clone();
+            opt_props
+        })
+}
+
+/// Options without a section, followed by options in sections.
+fn config() -> impl Strategy<Value = Vec<OptionProperties>> {
+    let top = prop::collection::vec(option(None), 0..8);
+    let sections = prop::collection::vec(
+        "[a-z][a-z0-9_]{0,8}".prop_flat_map(|name| prop::collection::vec(option(Some(name)), 1..5)),
+        0..4,
+    );
+    (top, sections).prop_map(|(mut vec, sections)| {
+        vec.extend(sections.into_iter().flatten());
+        vec
+    })
+}
+
+proptest! {
+    #[test]
+    fn write_and_reparse(vec in config(), delim in prop::sample::select(vec![',', ';', '|'])) {
+        let mut out = Vec::new();
+        configster::write_to(&vec, &mut out, delim).unwrap();
+        let parsed = configster::parse_reader(out.as_slice(), delim).unwrap();
+        prop_assert_eq!(parsed, vec);
+    }
+}